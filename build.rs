@@ -0,0 +1,78 @@
+//! Precompute the substitution positions of the default templates,
+//! so they don't have to be scanned at runtime (see `html::precompiled_substitutions`)
+//! Also captures the git commit noos is built from as `NOOS_GIT_HASH` (see `html::NOOS_BUILD`)
+
+use std::path::Path;
+
+/// A substitution as `(start, end, specifier name, truncation limit)`
+type RawSubstitution = (usize, usize, String, Option<usize>);
+
+fn main() {
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+
+    for name in ["page", "item", "group"] {
+        let template_path = format!("templates/{name}.html");
+        println!("cargo::rerun-if-changed={template_path}");
+
+        let template = std::fs::read_to_string(&template_path).unwrap();
+        let substitutions = scan_substitutions(&template);
+        let bytes = bincode::encode_to_vec(&substitutions, bincode::config::standard()).unwrap();
+
+        let out_path = Path::new(&out_dir).join(format!("{name}_substitutions.bin"));
+        std::fs::write(out_path, bytes).unwrap();
+    }
+
+    println!("cargo::rustc-env=NOOS_GIT_HASH={}", git_hash());
+    for path in [".git/HEAD", ".git/refs"] {
+        if Path::new(path).exists() {
+            println!("cargo::rerun-if-changed={path}");
+        }
+    }
+}
+
+/// Get the short hash of the current git commit, or "unknown" (e.g. outside of a git checkout)
+fn git_hash() -> String {
+    std::process::Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .filter(|hash| !hash.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Find all unescaped `${specifier}` and `${specifier:N}` sequences in a template
+/// NOTE: Names aren't checked here, unknown specifiers are dropped when decoding
+fn scan_substitutions(template: &str) -> Vec<RawSubstitution> {
+    let mut substitutions = Vec::new();
+
+    for (start, _) in template.match_indices("${") {
+        if start > 0 && template.as_bytes()[start - 1] == b'\\' {
+            continue;
+        }
+        let rest = &template[start + 2..];
+        let Some(len) = rest.find('}') else {
+            continue;
+        };
+
+        let (name, limit) = match rest[..len].split_once(':') {
+            Some((name, limit))
+                if !limit.is_empty() && limit.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                (name, limit.parse().ok())
+            }
+            Some(_) => continue,
+            None => (&rest[..len], None),
+        };
+        if name.is_empty() || !name.bytes().all(|b| b.is_ascii_lowercase() || b == b'_') {
+            continue;
+        }
+
+        let end = start + 2 + len + 1;
+        substitutions.push((start, end, name.to_string(), limit));
+    }
+
+    substitutions
+}
@@ -1,11 +1,23 @@
 //! Command line interface parsing and validation
 
-use crate::LogLevel;
-use clap::*;
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use crate::{
+    LogLevel,
+    logger::{self, ColorChoice, LogFormat},
+};
+use clap::{parser::ValueSource, *};
 
 /// A pragmatic RSS aggregator with a browser interface and no built-in reader.
 #[derive(Parser, Debug, Clone)]
-#[command(author, version, about)]
+#[command(
+    author,
+    version,
+    about,
+    after_help = "Global options can also be set in `$config_dir/noos/config.toml`,\n\
+                  as can `refresh_interval` (the default of `serve --refresh`).\n\
+                  Precedence: command line > config file > built-in default."
+)]
 pub struct Args {
     /// Subcommand to execute. Defaults to starting the web server if none provided.
     #[command(subcommand)]
@@ -19,6 +31,48 @@ pub struct Args {
     #[arg(short = 'v', long = "verbosity", value_name = "0-3", default_value_t = LogLevel::Debug, verbatim_doc_comment)]
     pub verbosity: LogLevel,
 
+    /// Only log errors and don't print feed listings, e.g. for cron jobs
+    /// Takes precedence over `--verbosity`
+    #[arg(short = 'q', long = "quiet", verbatim_doc_comment)]
+    pub quiet: bool,
+
+    /// Also append all logged messages to this file (without colors)
+    #[arg(long = "log-file", value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Rotate the log file once it would grow beyond this size (in bytes)
+    #[arg(long = "log-max-size", value_name = "BYTES")]
+    pub log_max_size: Option<u64>,
+
+    /// Number of rotated log files to keep (`<log-file>.1`, `<log-file>.2`, ...)
+    #[arg(long = "log-max-files", value_name = "N", default_value_t = 5)]
+    pub log_max_files: usize,
+
+    /// Format of logged lines (on stderr and in the log file)
+    #[arg(long = "log-format", value_enum, default_value_t)]
+    pub log_format: LogFormat,
+
+    /// Format of the timestamp of logged lines (see `chrono::format::strftime`)
+    #[arg(
+        long = "log-timestamp-format",
+        value_name = "FORMAT",
+        default_value = crate::logger::DEFAULT_TIMESTAMP_FORMAT
+    )]
+    pub log_timestamp_format: String,
+
+    /// Don't start logged lines with a timestamp (e.g. when logging to journald)
+    #[arg(long = "log-no-timestamp")]
+    pub log_no_timestamp: bool,
+
+    /// Whether to colorize logged lines
+    /// `auto` colorizes terminal output, respecting `NO_COLOR`, `CLICOLOR` and `CLICOLOR_FORCE`
+    #[arg(long = "color", value_enum, default_value_t, verbatim_doc_comment)]
+    pub color: ColorChoice,
+
+    /// Don't include the level (like `[info]`) in logged lines
+    #[arg(long = "log-no-level")]
+    pub log_no_level: bool,
+
     /// Path to the html template for item/article rendering
     #[arg(long = "item-template")]
     pub item_template: Option<std::path::PathBuf>,
@@ -26,11 +80,162 @@ pub struct Args {
     /// Path to the html template for the page surrounding the articles
     #[arg(long = "page-template")]
     pub page_template: Option<std::path::PathBuf>,
+
+    /// Path to the html template for group headers (see `${items_by_channel}`/`${items_by_day}`)
+    #[arg(long = "group-template")]
+    pub group_template: Option<std::path::PathBuf>,
+
+    /// Render html in item descriptions and content sanitized (links, emphasis, lists, images)
+    /// instead of fully escaping it
+    #[arg(long = "sanitize-html", verbatim_doc_comment)]
+    pub sanitize_html: bool,
+
+    /// Format of item dates (`${date}`, see `chrono::format::strftime`), e.g. `%d %b %Y`
+    #[arg(
+        long = "date-format",
+        value_name = "FORMAT",
+        default_value = crate::html::DEFAULT_DATE_FORMAT
+    )]
+    pub date_format: String,
+
+    /// Format of item times (`${time}`, see `chrono::format::strftime`), e.g. `%I:%M %p`
+    #[arg(
+        long = "time-format",
+        value_name = "FORMAT",
+        default_value = crate::html::DEFAULT_TIME_FORMAT
+    )]
+    pub time_format: String,
+
+    /// Text rendered for missing item titles and descriptions (may be empty)
+    /// Defaults to "(No title)" and "(No description)"
+    #[arg(long = "empty-placeholder", value_name = "TEXT", verbatim_doc_comment)]
+    pub empty_placeholder: Option<String>,
+
+    /// User-defined template variables, available as `${custom:KEY}` in all templates
+    /// Only set via the `[template_vars]` table of the config file
+    #[arg(skip)]
+    pub template_vars: HashMap<String, String>,
+
+    /// Check the templates for unknown or malformed format specifiers, then exit
+    /// Exits with status 1 if any problems were found. No feeds are fetched.
+    #[arg(long = "check-templates", verbatim_doc_comment)]
+    pub check_templates: bool,
+
+    /// Replace feed URLs in the channels file with the canonical URL
+    /// a feed advertises for itself (via `<atom:link rel="self">`)
+    #[arg(long = "update-canonical", verbatim_doc_comment)]
+    pub update_canonical: bool,
+
+    /// Replace feed URLs in the channels file with the final URL
+    /// a feed was redirected to (via 301/302)
+    #[arg(long = "update-moved", verbatim_doc_comment)]
+    pub update_moved: bool,
+
+    /// Don't access the network, only use cached copies of feeds
+    /// Feeds that were never fetched before are skipped
+    #[arg(long = "offline", alias = "no-network", verbatim_doc_comment)]
+    pub offline: bool,
+
+    /// Reuse cached feeds fetched less than this many seconds ago,
+    /// without any network access. 0 disables this cache
+    #[arg(
+        long = "cache-ttl",
+        value_name = "SECONDS",
+        default_value_t = 0,
+        verbatim_doc_comment
+    )]
+    pub cache_ttl: u64,
+
+    /// Always revalidate cached feeds, even if the server declared them fresh
+    /// (via the `Cache-Control: max-age` or `Expires` response headers)
+    #[arg(long = "ignore-cache-control", verbatim_doc_comment)]
+    pub ignore_cache_control: bool,
+
+    /// Timeout for connecting to feed servers (in seconds)
+    #[arg(long = "connect-timeout", value_name = "SECONDS", default_value_t = 5)]
+    pub connect_timeout: u64,
+
+    /// Timeout for fetching a feed, including reading the response (in seconds)
+    #[arg(long = "read-timeout", value_name = "SECONDS", default_value_t = 5)]
+    pub read_timeout: u64,
+
+    /// Number of retries for feeds failing with connection errors, timeouts or 5xx responses
+    #[arg(long = "max-retries", default_value_t = 2)]
+    pub max_retries: u32,
+
+    /// Item timestamps further in the future than this are replaced by now (in seconds)
+    #[arg(long = "max-future-skew", value_name = "SECONDS", default_value_t = 24 * 60 * 60)]
+    pub max_future_skew: u64,
+
+    /// Item timestamps before this year are replaced by now
+    #[arg(long = "min-year", value_name = "YEAR", default_value_t = 1990)]
+    pub min_year: i32,
+
+    /// Seed for all randomized behavior, so runs with the same inputs give identical output
+    /// Defaults to a random seed (logged at debug level)
+    #[arg(long = "seed", value_name = "N", verbatim_doc_comment)]
+    pub seed: Option<u64>,
+
+    /// The `User-Agent` header sent when fetching feeds
+    #[arg(long = "user-agent", default_value = crate::data::DEFAULT_USER_AGENT)]
+    pub user_agent: String,
+
+    /// Maximum number of redirects followed per feed
+    #[arg(long = "max-redirects", default_value_t = 10)]
+    pub max_redirects: usize,
+
+    /// Proxy URL used for all requests (`http://`, `https://` or `socks5://`)
+    /// Defaults to the `HTTP_PROXY`/`HTTPS_PROXY` environment variables
+    #[arg(long = "proxy", value_name = "URL", verbatim_doc_comment)]
+    pub proxy: Option<String>,
+
+    /// Remove tracking query parameters from item links
+    #[arg(long = "strip-tracking-params")]
+    pub strip_tracking_params: bool,
+
+    /// Query parameters considered tracking parameters (comma separated)
+    /// A trailing `*` matches any parameter with that prefix
+    #[arg(
+        long = "tracking-params",
+        value_delimiter = ',',
+        default_values_t = DEFAULT_TRACKING_PARAMS.map(String::from),
+        verbatim_doc_comment
+    )]
+    pub tracking_params: Vec<String>,
+
+    /// Number of channels file backups to keep, made whenever the channels file is changed
+    /// 0 disables backups
+    #[arg(
+        long = "backups",
+        value_name = "N",
+        default_value_t = crate::data::DEFAULT_BACKUP_COUNT,
+        verbatim_doc_comment
+    )]
+    pub backups: usize,
+
+    /// Channels file to use instead of `$config_dir/noos/channels.txt`
+    /// Applies to all commands that read or change the channels file
+    #[arg(long = "channels", value_name = "PATH", verbatim_doc_comment)]
+    pub channels: Option<PathBuf>,
+
+    /// Directory to use instead of `$config_dir/noos` for the config file,
+    /// templates, channels file, backups and cache
+    #[arg(long = "config-dir", value_name = "PATH", verbatim_doc_comment)]
+    pub config_dir: Option<PathBuf>,
+
+    /// Don't write the channels file, backups or exported files, only log what would change
+    #[arg(long = "dry-run")]
+    pub dry_run: bool,
     // TODO: cli option for timelining strategy (fallback timestamps)
     //       options could be: default to now-1min, discard item, or:
     //       "sprinkle" (evenly distribute articles with missing timestamps between other articles)
 }
 
+/// Query parameters stripped from item links by `--strip-tracking-params` by default
+pub const DEFAULT_TRACKING_PARAMS: [&str; 9] = [
+    "utm_*", "fbclid", "gclid", "dclid", "msclkid", "mc_cid", "mc_eid", "igshid", "yclid",
+];
+
 #[derive(Subcommand, Debug, Clone)]
 pub enum Subcommand {
     /// Start the web server
@@ -47,53 +252,567 @@ pub enum Subcommand {
         /// Open the web interface in the default browser
         #[arg(short = 'o', long = "open", default_value_t = true)]
         open: bool,
+
+        /// Don't open the web interface, overriding `--open` (e.g. for headless use)
+        /// Setting the `NOOS_NO_OPEN` environment variable has the same effect
+        #[arg(long = "no-open", overrides_with = "open", verbatim_doc_comment)]
+        no_open: bool,
+
+        /// How often to refetch all feeds and re-render the page (`0s` to never refresh)
+        #[arg(long = "refresh", value_name = "DURATION", default_value = "30m", value_parser = parse_duration)]
+        refresh: Duration,
+
+        /// Only show items from this long ago until now (e.g. `90m`, `24h`, `7d`)
+        #[arg(long = "since", value_name = "DURATION", value_parser = parse_duration)]
+        since: Option<Duration>,
+
+        /// Read newline-separated feed URLs from stdin instead of the channels file
+        #[arg(long = "stdin")]
+        stdin: bool,
     },
 
     /// Dump the rendered html of the web interface to a file
     #[command(alias = "d")]
-    Dump {
-        /// File to write the dumped HTML to
-        #[arg(short = 'f', long = "file", default_value = "noos.html")]
-        file: std::path::PathBuf,
-    },
+    Dump(DumpArgs),
+
     /// Manage individual feeds
     #[command(subcommand)]
     Feed(FeedSubcommand),
+
+    /// Inspect the configuration
+    #[command(subcommand)]
+    Config(ConfigSubcommand),
+
+    /// Restore the most recent backup of the channels file
+    /// The current channels file is backed up first, so an undo can be undone
+    #[command(verbatim_doc_comment)]
+    Undo,
+
+    /// Print a shell completion script to stdout
+    #[command(hide = true)]
+    Completions { shell: clap_complete::Shell },
+
+    /// Print the man page in roff format to stdout, or write it to a file
+    #[command(hide = true)]
+    Manpage { file: Option<PathBuf> },
+}
+
+/// Arguments of the dump command
+#[derive(Args, Debug, Clone)]
+pub struct DumpArgs {
+    /// File to write the dumped HTML to
+    #[arg(short = 'f', long = "file", default_value = "noos.html")]
+    pub file: PathBuf,
+
+    /// Only include items from feeds with this tag (repeatable, matches any)
+    #[arg(short = 't', long = "tag")]
+    pub tags: Vec<String>,
+
+    /// Split the timeline into pages of N items each (`noos.html`, `noos-2.html`, ...)
+    /// 0 means a single page
+    #[arg(
+        long = "page-size",
+        value_name = "N",
+        default_value_t = 0,
+        verbatim_doc_comment
+    )]
+    pub page_size: usize,
+
+    /// Output format of the dumped timeline
+    #[arg(long = "format", value_enum, default_value_t)]
+    pub format: OutputFormat,
+
+    /// Title of the merged feed (for the rss and jsonfeed formats)
+    #[arg(long = "feed-title", default_value = "noos")]
+    pub feed_title: String,
+
+    /// Load the timeline from this file instead of fetching feeds, if it exists,
+    /// otherwise fetch feeds and save the timeline to it (e.g. for working on templates)
+    #[arg(long = "cache-file", value_name = "PATH", verbatim_doc_comment)]
+    pub cache_file: Option<PathBuf>,
+
+    /// Only include items from this long ago until now (e.g. `90m`, `24h`, `7d`)
+    #[arg(long = "since", value_name = "DURATION", value_parser = parse_duration)]
+    pub since: Option<Duration>,
+
+    /// Only include items whose title or description contains this text (case insensitive)
+    #[arg(long = "query", value_name = "PATTERN")]
+    pub query: Option<String>,
+
+    /// Match `--query` as a regular expression instead
+    #[arg(long = "regex", requires = "query")]
+    pub regex: bool,
+
+    /// Fail (with exit status 1) without writing anything if no items remain,
+    /// e.g. if all feeds are empty or filtered out
+    #[arg(long = "fail-on-empty", verbatim_doc_comment)]
+    pub fail_on_empty: bool,
+
+    /// Open the dumped file in the default browser afterwards
+    #[arg(short = 'o', long = "open")]
+    pub open: bool,
+
+    /// Don't open the dumped file, overriding `--open` (e.g. for headless use)
+    /// Setting the `NOOS_NO_OPEN` environment variable has the same effect
+    #[arg(long = "no-open", overrides_with = "open", verbatim_doc_comment)]
+    pub no_open: bool,
+
+    /// Read newline-separated feed URLs from stdin instead of the channels file
+    /// (e.g. `cat urls.txt | noos dump --stdin`)
+    #[arg(long = "stdin", verbatim_doc_comment)]
+    pub stdin: bool,
+}
+
+/// Parse a human readable duration, like `90s`, `15m`, `24h`, `7d`, `2w` or `1d12h`
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err("empty duration".to_string());
+    }
+
+    let mut seconds: u64 = 0;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            return Err(format!(
+                "invalid duration '{s}', expected e.g. `24h` or `7d`"
+            ));
+        }
+        let (number, tail) = rest.split_at(digits);
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+
+        let unit_seconds = match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            "w" => 7 * 24 * 60 * 60,
+            _ => {
+                return Err(format!(
+                    "invalid duration unit '{unit}' in '{s}' (use s, m, h, d or w)"
+                ));
+            }
+        };
+        let number: u64 = number
+            .parse()
+            .map_err(|e| format!("invalid duration '{s}': {e}"))?;
+        seconds = number
+            .checked_mul(unit_seconds)
+            .and_then(|n| seconds.checked_add(n))
+            .ok_or_else(|| format!("duration '{s}' is too long"))?;
+        rest = tail;
+    }
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Output formats of the dump command, see `output`
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Html rendered from the page and item templates
+    #[default]
+    Html,
+    /// A JSON array of items
+    Json,
+    /// A merged RSS 2.0 feed
+    Rss,
+    /// A merged JSON Feed 1.1 document
+    Jsonfeed,
+}
+
+/// Formats of the feed export command
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// An OPML outline, with feed titles fetched from the feeds
+    #[default]
+    Opml,
+    /// A plain list of feed URLs, one per line
+    Txt,
+    /// A JSON array of `{url, name, tags}` objects
+    Json,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigSubcommand {
+    /// Show the resolved config directory, config file, channels file, templates and cache,
+    /// and whether each exists
+    ///
+    /// The config directory is resolved in this order:
+    ///   1. `--config-dir`
+    ///   2. `$XDG_CONFIG_HOME/noos` (Linux), if `XDG_CONFIG_HOME` is set
+    ///   3. The platform default, e.g. `~/.config/noos` (Linux),
+    ///      `~/Library/Application Support/noos` (macOS) or `%APPDATA%\noos` (Windows)
+    ///
+    /// The channels file and templates can each be overridden individually, see `--help`.
+    #[command(verbatim_doc_comment)]
+    Path,
 }
 
 #[derive(Subcommand, Debug, Clone)]
 pub enum FeedSubcommand {
     /// List all subscribed feeds
-    List,
+    List {
+        /// Fetch each feed and show its title and item count (or `[unreachable]`)
+        #[arg(long = "verbose")]
+        verbose: bool,
+    },
+    /// List the subscribed feeds whose URL, name or title contains a term (case insensitive)
+    /// Titles are only known for feeds that were fetched (and cached) before
+    #[command(verbatim_doc_comment)]
+    Search { term: String },
+    /// Find the feeds a website advertises (via `<link rel="alternate">`) and print them
+    Discover {
+        /// The URL of the website, e.g. its homepage
+        url: String,
+        /// Add the first discovered feed to the channels file
+        #[arg(long = "add")]
+        add: bool,
+    },
     /// Add a new feed by URL
-    Add { feed: String },
+    Add {
+        feed: String,
+        /// Fetch the feed and make sure it parses before adding it
+        #[arg(long = "verify")]
+        verify: bool,
+    },
     /// Remove a feed by URL
     Remove { feed: String },
-    /// Import all feeds from an OPML file. Note: see `$config_dir/noos/channels.txt`
-    Import { file: String },
-    /// Export all feeds to an OPML file. Note: see `$config_dir/noos/channels.txt`
-    Export { file: String },
+    /// Fetch a single feed by URL and report on it, without changing the channels file
+    Test { feed: String },
+    /// Set the display name of a feed by URL (an empty name removes it)
+    Rename { feed: String, name: String },
+    /// Add tags to a feed by URL
+    Tag {
+        feed: String,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// Remove tags from a feed by URL
+    Untag {
+        feed: String,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// Remove duplicate feed URLs from the channels file, keeping comments and order
+    Dedupe,
+    /// Import all feeds from an OPML, JSON or text file (detected from its content).
+    /// Note: see `$config_dir/noos/channels.txt`
+    Import {
+        file: String,
+
+        /// Pick the feeds to import from a checklist (ignored without a terminal)
+        #[arg(short = 'i', long = "interactive")]
+        interactive: bool,
+    },
+    /// Export all feeds to an OPML, text or JSON file. Note: see `$config_dir/noos/channels.txt`
+    Export {
+        file: String,
+
+        /// Format of the exported file
+        #[arg(long = "format", value_enum, default_value_t)]
+        format: ExportFormat,
+    },
+}
+
+/// Contents of the config file at `$config_dir/noos/config.toml`
+/// Keys mirror the global command line arguments (in snake_case), plus:
+/// - `template_vars`: a table of `${custom:KEY}` values
+/// - `refresh_interval`: the default `serve --refresh` (e.g. `"15m"`)
+///
+/// There are no `limit` or `timezone` keys, as there are no such arguments.
+/// Unknown keys are rejected.
+/// Precedence: command line > config file > built-in default
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub verbosity: Option<LogLevel>,
+    pub quiet: Option<bool>,
+    pub log_file: Option<PathBuf>,
+    pub log_max_size: Option<u64>,
+    pub log_max_files: Option<usize>,
+    pub log_format: Option<LogFormat>,
+    pub log_timestamp_format: Option<String>,
+    pub log_no_timestamp: Option<bool>,
+    pub log_no_level: Option<bool>,
+    pub color: Option<ColorChoice>,
+    pub item_template: Option<PathBuf>,
+    pub page_template: Option<PathBuf>,
+    pub group_template: Option<PathBuf>,
+    pub sanitize_html: Option<bool>,
+    pub date_format: Option<String>,
+    pub time_format: Option<String>,
+    pub empty_placeholder: Option<String>,
+    pub template_vars: Option<HashMap<String, String>>,
+    pub update_canonical: Option<bool>,
+    pub update_moved: Option<bool>,
+    pub offline: Option<bool>,
+    pub cache_ttl: Option<u64>,
+    pub ignore_cache_control: Option<bool>,
+    pub connect_timeout: Option<u64>,
+    pub read_timeout: Option<u64>,
+    pub max_retries: Option<u32>,
+    pub max_future_skew: Option<u64>,
+    pub min_year: Option<i32>,
+    pub seed: Option<u64>,
+    pub user_agent: Option<String>,
+    pub max_redirects: Option<usize>,
+    pub proxy: Option<String>,
+    pub strip_tracking_params: Option<bool>,
+    pub tracking_params: Option<Vec<String>>,
+    pub backups: Option<usize>,
+    pub channels: Option<PathBuf>,
+    pub refresh_interval: Option<String>,
+}
+
+/// Write the completion script for `shell` to stdout
+pub fn print_completions(shell: clap_complete::Shell) {
+    clap_complete::generate(
+        shell,
+        &mut Args::command(),
+        env!("CARGO_BIN_NAME"),
+        &mut std::io::stdout(),
+    );
+}
+
+/// Render the man page to `file`, or to stdout if none is given
+pub fn write_manpage(file: Option<&std::path::Path>) -> std::io::Result<()> {
+    let man = clap_mangen::Man::new(Args::command());
+    match file {
+        Some(path) => man.render(&mut std::fs::File::create(path)?),
+        None => man.render(&mut std::io::stdout()),
+    }
+}
+
+/// Parse command line arguments, falling back to values from the config file
+/// for any global arguments that weren't explicitly given on the command line.
+/// Exits on failure
+pub fn parse() -> Args {
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    // completions and man page must not depend on the config dir, so exit before loading it
+    if let Some(Subcommand::Completions { shell }) = args.command {
+        print_completions(shell);
+        logger::exit(0);
+    }
+    if let Some(Subcommand::Manpage { file }) = &args.command {
+        if let Err(e) = write_manpage(file.as_deref()) {
+            Args::command()
+                .error(
+                    error::ErrorKind::Io,
+                    format!("failed to write man page: {e}"),
+                )
+                .exit();
+        }
+        logger::exit(0);
+    }
+
+    // the config file itself is located in the config dir
+    if let Some(dir) = &args.config_dir {
+        crate::data::init_config_dir(dir.clone()).unwrap();
+    }
+    let config = load_config();
+
+    macro_rules! apply_config {
+        ($($field:ident),* $(,)?) => {$(
+            if matches.value_source(stringify!($field)) != Some(ValueSource::CommandLine)
+                && let Some(value) = config.$field
+            {
+                args.$field = value.into();
+            }
+        )*};
+    }
+
+    apply_config!(
+        verbosity,
+        quiet,
+        log_file,
+        log_max_size,
+        log_max_files,
+        log_format,
+        log_timestamp_format,
+        log_no_timestamp,
+        log_no_level,
+        color,
+        item_template,
+        page_template,
+        group_template,
+        sanitize_html,
+        date_format,
+        time_format,
+        empty_placeholder,
+        update_canonical,
+        update_moved,
+        offline,
+        cache_ttl,
+        ignore_cache_control,
+        connect_timeout,
+        read_timeout,
+        max_retries,
+        max_future_skew,
+        min_year,
+        seed,
+        user_agent,
+        max_redirects,
+        proxy,
+        strip_tracking_params,
+        tracking_params,
+        backups,
+        channels,
+    );
+
+    // Not a command line argument, so there is nothing to take precedence
+    if let Some(template_vars) = config.template_vars {
+        args.template_vars = template_vars;
+    }
+
+    if let Some(Subcommand::Serve { refresh, .. }) = &mut args.command
+        && let Some(serve_matches) = matches.subcommand_matches("serve")
+        && serve_matches.value_source("refresh") != Some(ValueSource::CommandLine)
+        && let Some(interval) = &config.refresh_interval
+    {
+        *refresh = parse_duration(interval).unwrap_or_else(|e| {
+            Args::command()
+                .error(
+                    error::ErrorKind::ValueValidation,
+                    format!("invalid refresh_interval '{interval}' in config file: {e}"),
+                )
+                .exit()
+        });
+    }
+
+    if args.quiet {
+        args.verbosity = LogLevel::Error;
+    }
+
+    args
+}
+
+/// Load the config file from `$config_base/config.toml`, see `data::config_base`
+/// A missing config file is not an error, and yields the default (empty) config.
+/// NOTE: This runs before the logger is initialized, so errors are printed directly.
+/// Exits on failure
+fn load_config() -> Config {
+    let Some(path) = crate::data::config_base().map(|dir| dir.join("config.toml")) else {
+        return Config::default();
+    };
+
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Config::default(),
+        Err(e) => {
+            eprintln!(
+                "Fatal: Failed to read config file '{}': {e}",
+                path.display()
+            );
+            logger::exit(1);
+        }
+    };
+
+    toml::from_str(&contents).unwrap_or_else(|e| {
+        eprintln!(
+            "Fatal: Failed to parse config file '{}': {e}",
+            path.display()
+        );
+        logger::exit(1);
+    })
 }
 
 /// Semantically validate and process cli arguments
 /// Exits on failure
 pub fn validate(args: &Args) -> Args {
-    args.clone() // No proper validation needed just yet
+    let fail = |message: &str| -> ! {
+        Args::command()
+            .error(error::ErrorKind::ValueValidation, message)
+            .exit()
+    };
+
+    if args.connect_timeout == 0 {
+        fail("--connect-timeout must be a positive number of seconds");
+    }
+    if args.read_timeout == 0 {
+        fail("--read-timeout must be a positive number of seconds");
+    }
+    if args.log_max_size == Some(0) {
+        fail("--log-max-size must be a positive number of bytes");
+    }
+    if let Err(e) = crate::logger::validate_timestamp_format(&args.log_timestamp_format) {
+        fail(&format!("--log-timestamp-format: {e}"));
+    }
+    if let Err(e) = crate::logger::validate_timestamp_format(&args.date_format) {
+        fail(&format!("--date-format: {e}"));
+    }
+    if let Err(e) = crate::logger::validate_timestamp_format(&args.time_format) {
+        fail(&format!("--time-format: {e}"));
+    }
+    if let Some(proxy) = &args.proxy {
+        const PROXY_SCHEMES: [&str; 5] = ["http", "https", "socks4", "socks5", "socks5h"];
+        if let Err(e) = reqwest::Proxy::all(proxy) {
+            fail(&format!("invalid --proxy URL '{proxy}': {e}"));
+        }
+        // Proxy URLs without scheme default to `http://`
+        if proxy.contains("://")
+            && let Ok(url) = url::Url::parse(proxy)
+            && !PROXY_SCHEMES.contains(&url.scheme())
+        {
+            fail(&format!("unsupported --proxy scheme '{}'", url.scheme()));
+        }
+    }
+
+    match &args.command {
+        Some(Subcommand::Feed(FeedSubcommand::Add { feed, .. }))
+        | Some(Subcommand::Feed(FeedSubcommand::Remove { feed })) => {
+            if let Err(e) = crate::data::validate_feed_url(feed) {
+                let feed = crate::data::redact_url(feed);
+                fail(&format!("invalid feed URL '{feed}': {e}"));
+            }
+        }
+        Some(Subcommand::Dump(dump_args)) => {
+            let parent = dump_args
+                .file
+                .parent()
+                .filter(|p| !p.as_os_str().is_empty());
+            if let Some(parent) = parent
+                && let Err(e) = std::fs::create_dir_all(parent)
+            {
+                let parent = parent.display();
+                fail(&format!("can't create output directory '{parent}': {e}"));
+            }
+        }
+        Some(Subcommand::Serve { bind, .. }) => {
+            if let Err(e) = bind.parse::<std::net::IpAddr>() {
+                fail(&format!("invalid --bind address '{bind}': {e}"));
+            }
+        }
+        _ => {}
+    }
+
+    args.clone()
 }
 
 impl Default for Subcommand {
     /// Default to dumping the rendered HTML to "noos.html"
     fn default() -> Self {
-        Subcommand::Dump {
+        Subcommand::Dump(DumpArgs {
             file: "noos.html".into(),
-        }
-        // TODO: Set default subcommand to serve once server is implemented
-        // Subcommand::Serve {
-        //     port: 9005,
-        //     bind: "127.0.0.1".into(),
-        //     open: true,
-        // }
+            tags: Vec::new(),
+            page_size: 0,
+            format: OutputFormat::Html,
+            feed_title: "noos".into(),
+            cache_file: None,
+            since: None,
+            query: None,
+            regex: false,
+            fail_on_empty: false,
+            open: false,
+            no_open: false,
+            stdin: false,
+        })
     }
 }
-
-// TODO: Add config file support
@@ -5,14 +5,16 @@
 
 use std::{
     borrow::Cow,
-    collections::HashSet,
+    collections::HashMap,
     path::{Path, PathBuf},
+    sync::{LazyLock, OnceLock},
 };
 
 use html_escape::encode_safe;
 use regex::Regex;
 
-use crate::data::TimelineItem;
+use crate::data::{self, TimelineItem};
+use crate::logger;
 
 #[allow(unused_imports)]
 use crate::{debug, error, info, log, warn};
@@ -21,6 +23,8 @@ use crate::{debug, error, info, log, warn};
 type PageSubst = Substitution<PageFormatSpecifier>;
 /// A shorthand for `Substitution<ItemFormatSpecifier>`
 type ItemSubst = Substitution<ItemFormatSpecifier>;
+/// A shorthand for `Substitution<GroupFormatSpecifier>`
+type GroupSubst = Substitution<GroupFormatSpecifier>;
 
 /// A minimally pre-parsed page template, that allows to
 /// calculate positions for substitutions only once.
@@ -28,6 +32,8 @@ type ItemSubst = Substitution<ItemFormatSpecifier>;
 pub struct PageTemplate {
     template: String,
     substitutions: Vec<PageSubst>,
+    /// User-defined `${custom:KEY}` substitutions, see `init_template_vars`
+    custom: Vec<CustomSubstitution>,
 }
 
 /// A minimally pre-parsed item template, that allows to
@@ -36,6 +42,64 @@ pub struct PageTemplate {
 pub struct ItemTemplate {
     template: String,
     substitutions: Vec<ItemSubst>,
+    /// User-defined `${custom:KEY}` substitutions, see `init_template_vars`
+    custom: Vec<CustomSubstitution>,
+    /// Whether to sanitize (rather than escape) feed-provided html, see `Encoding`
+    sanitize_html: bool,
+    /// `chrono` formats of `${date}` and `${time}`
+    date_format: String,
+    time_format: String,
+}
+
+/// The default `chrono` format of the `${date}` item specifier
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+/// The default `chrono` format of the `${time}` item specifier
+pub const DEFAULT_TIME_FORMAT: &str = "%H:%M:%S";
+
+/// Everything needed to render a (single) page, see `PageTemplate`
+#[derive(Debug, Clone, Copy)]
+pub struct PageContext<'a> {
+    /// The items on this page
+    pub items: &'a [TimelineItem],
+    pub item_template: &'a ItemTemplate,
+    pub group_template: &'a GroupTemplate,
+    /// Relative file names of the neighbouring pages, if paginated
+    pub prev_page: Option<&'a str>,
+    pub next_page: Option<&'a str>,
+}
+
+/// A minimally pre-parsed template for the header of a group of items,
+/// e.g. all items of a channel, see `PageFormatSpecifier::ItemsByChannel`
+#[derive(Debug)]
+pub struct GroupTemplate {
+    template: String,
+    substitutions: Vec<GroupSubst>,
+    /// User-defined `${custom:KEY}` substitutions, see `init_template_vars`
+    custom: Vec<CustomSubstitution>,
+}
+
+/// The values available to a `GroupTemplate`
+#[derive(Debug, Clone, Copy)]
+pub struct GroupHeader<'a> {
+    pub title: &'a str,
+    pub link: &'a str,
+    pub item_count: usize,
+}
+
+impl ItemTemplate {
+    /// Render feed-provided html in descriptions sanitized instead of fully escaped
+    pub fn sanitize_html(mut self, sanitize_html: bool) -> Self {
+        self.sanitize_html = sanitize_html;
+        self
+    }
+
+    /// Format `${date}` and `${time}` with these `chrono` formats instead of the defaults
+    /// The formats are expected to be valid, see `cli::validate`
+    pub fn datetime_formats(mut self, date_format: &str, time_format: &str) -> Self {
+        self.date_format = date_format.to_string();
+        self.time_format = time_format.to_string();
+        self
+    }
 }
 
 impl Template for ItemTemplate {
@@ -48,33 +112,19 @@ impl Template for ItemTemplate {
         let template = template.to_string();
         let mut substitutions = Vec::new();
 
-        use ItemFormatSpecifier::*;
-        for specifier in [
-            Title,
-            Description,
-            Source,
-            Link,
-            Date,
-            Time,
-            Timestamp,
-            ChannelLink,
-        ] {
-            substitutions.extend(
-                find_format_specifiers(&template, specifier)
-                    .into_iter()
-                    .map(|(start, end)| Substitution {
-                        start,
-                        end,
-                        specifier,
-                    }),
-            );
+        for &specifier in ItemFormatSpecifier::ALL {
+            substitutions.extend(find_format_specifiers(&template, specifier));
         }
 
         substitutions.sort_by_key(|s| s.start);
 
         Self {
+            custom: find_custom_specifiers(&template),
             template: template.to_string(),
             substitutions,
+            sanitize_html: false,
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            time_format: DEFAULT_TIME_FORMAT.to_string(),
         }
     }
 
@@ -82,80 +132,120 @@ impl Template for ItemTemplate {
         let template = std::fs::read_to_string(path).unwrap_or_else(|e| {
             error!("Failed to read template file: {e}");
             error!("Exiting...");
-            std::process::exit(1);
+            logger::exit(1);
         });
 
         Self::parse(template)
     }
 
-    #[rustfmt::skip]
     fn render<'a>(&self, item: Self::Deps<'a>) -> String {
-        // Made efficient by using size calculations.
-        // Start with template size, then for each substitution,
-        // add the size of the encoded string and subtract
-        // the size of the format specifier.
-        let mut size = self.template.len() as isize;
-
-        let (item_title, item_description, item_source, item_link, item_date, item_time, item_timestamp, item_channel_link) = (
-            item.title(), item.description(), item.source(), item.link(), item.date(), item.time(), item.timestamp.to_string(),
-            item.channel_url.clone()
-        );
+        let encoded = self
+            .substitutions
+            .iter()
+            .map(|subst| {
+                let value = subst
+                    .truncate(&subst.specifier.resolve(item, self))
+                    .into_owned();
+                match subst.specifier.encoding(self.sanitize_html) {
+                    Encoding::Escape => encode_safe(&value).into_owned(),
+                    Encoding::Sanitize => HTML_SANITIZER.clean(&value).to_string(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        render_substitutions(&self.template, &self.substitutions, &encoded, &self.custom)
+    }
 
-        // TODO: Refactor item rendering
+    fn validate(&self) -> Result<(), Vec<TemplateWarning>> {
+        validate_specifiers::<ItemFormatSpecifier>(&self.template)
+    }
+}
 
-        use ItemFormatSpecifier::*;
-        let (title_encoded, n1) = encode_specifier_with_size(&item_title, Title);
-        let (description_encoded, n2) = encode_specifier_with_size(&item_description, Description);
-        let (source_encoded, n3) = encode_specifier_with_size(&item_source, Source);
-        let (link_encoded, n4) = encode_specifier_with_size(&item_link, Link);
-        let (date_encoded, n5) = encode_specifier_with_size(&item_date, Date);
-        let (time_encoded, n6) = encode_specifier_with_size(&item_time, Time);
-        let (timestamp_encoded, n7) = encode_specifier_with_size(&item_timestamp, Timestamp);
-        let (channel_link_encoded, n8) = encode_specifier_with_size(&item_channel_link, ChannelLink);
-
-        for subst in &self.substitutions {
-            size += match subst.specifier {
-                Title => n1,
-                Description => n2,
-                Source => n3,
-                Link => n4,
-                Date => n5,
-                Time => n6,
-                Timestamp => n7,
-                ChannelLink => n8,
-            };
+impl Template for GroupTemplate {
+    type Deps<'a> = &'a GroupHeader<'a>;
+
+    fn parse<S>(template: S) -> Self
+    where
+        S: ToString,
+    {
+        let template = template.to_string();
+        let mut substitutions = Vec::new();
+
+        for &specifier in GroupFormatSpecifier::ALL {
+            substitutions.extend(find_format_specifiers(&template, specifier));
         }
 
-        // Now do the actual rendering with substitutions.
-        let mut rendered = String::with_capacity(size as usize);
-
-        // Build the final string
-        let mut last_pos = 0;
-        for subst in &self.substitutions {
-            let (start, end) = (subst.start, subst.end);
-            let encoded = match subst.specifier {
-                Title => &title_encoded,
-                Description => &description_encoded,
-                Source => &source_encoded,
-                Link => &link_encoded,
-                Date => &date_encoded,
-                Time => &time_encoded,
-                Timestamp => &timestamp_encoded,
-                ChannelLink => &channel_link_encoded,
-            };
+        substitutions.sort_by_key(|s| s.start);
 
-            rendered.push_str(&self.template[last_pos..start]);
-            rendered.push_str(encoded);
-            last_pos = end;
+        Self {
+            custom: find_custom_specifiers(&template),
+            template,
+            substitutions,
         }
-        rendered.push_str(&self.template[last_pos..]);
+    }
 
-        rendered
+    /// NOTE: Exits on file read error, see logging output.
+    fn parse_file<P: AsRef<std::path::Path>>(path: P) -> Self {
+        let template = std::fs::read_to_string(path).unwrap_or_else(|e| {
+            error!("Failed to read template file: {e}");
+            error!("Exiting...");
+            logger::exit(1);
+        });
+
+        Self::parse(template)
+    }
+
+    fn render<'a>(&self, header: Self::Deps<'a>) -> String {
+        let item_count = header.item_count.to_string();
+
+        use GroupFormatSpecifier::*;
+        let encoded = self
+            .substitutions
+            .iter()
+            .map(|subst| {
+                let value = match subst.specifier {
+                    Title => header.title,
+                    Link => header.link,
+                    ItemCount => &item_count,
+                };
+                encode_safe(&subst.truncate(value)).into_owned()
+            })
+            .collect::<Vec<_>>();
+
+        render_substitutions(&self.template, &self.substitutions, &encoded, &self.custom)
+    }
+
+    fn validate(&self) -> Result<(), Vec<TemplateWarning>> {
+        validate_specifiers::<GroupFormatSpecifier>(&self.template)
     }
 }
 
+/// The css inlined by `PageFormatSpecifier::Stylesheet`
+/// Read from `$config_base/style.css` if present, or the baked-in default
+static STYLESHEET: LazyLock<String> = LazyLock::new(|| {
+    let default = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/style.css"));
+    let Some(path) = get_user_config_file(STYLESHEET_FILE) else {
+        return default.to_string();
+    };
+
+    info!(
+        "Using custom stylesheet from config directory: '{}'",
+        path.display()
+    );
+    std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        warn!("Failed to read stylesheet, using default: {e}");
+        default.to_string()
+    })
+});
+
+/// The version of noos, see `PageFormatSpecifier::Version`
+const NOOS_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The git commit noos was built from (or "unknown"), see `PageFormatSpecifier::BuildInfo`
+const NOOS_BUILD: &str = env!("NOOS_GIT_HASH");
+
 impl Template for PageTemplate {
-    type Deps<'a> = (&'a [TimelineItem], &'a ItemTemplate);
+    type Deps<'a> = &'a PageContext<'a>;
 
     fn parse<S>(template: S) -> Self
     where
@@ -164,22 +254,14 @@ impl Template for PageTemplate {
         let template = template.to_string();
         let mut substitutions = Vec::new();
 
-        use PageFormatSpecifier::*;
-        for specifier in [Items, ItemCount, ChannelCount, Date, Time, Timestamp] {
-            substitutions.extend(
-                find_format_specifiers(&template, specifier)
-                    .into_iter()
-                    .map(|(start, end)| Substitution {
-                        start,
-                        end,
-                        specifier,
-                    }),
-            );
+        for &specifier in PageFormatSpecifier::ALL {
+            substitutions.extend(find_format_specifiers(&template, specifier));
         }
 
         substitutions.sort_by_key(|s| s.start);
 
         Self {
+            custom: find_custom_specifiers(&template),
             template: template.to_string(),
             substitutions,
         }
@@ -190,29 +272,37 @@ impl Template for PageTemplate {
         let template = std::fs::read_to_string(path).unwrap_or_else(|e| {
             error!("Failed to read template file: {e}");
             error!("Exiting...");
-            std::process::exit(1);
+            logger::exit(1);
         });
 
         Self::parse(template)
     }
 
-    fn render<'a>(&self, (content, item_template): Self::Deps<'a>) -> String {
-        let mut size = self.template.len() as isize;
+    fn render<'a>(&self, context: Self::Deps<'a>) -> String {
+        use PageFormatSpecifier::*;
+        let (content, item_template, group_template) =
+            (context.items, context.item_template, context.group_template);
 
         let items = content
             .iter()
             .map(|item| item_template.render(item))
             .collect::<String>();
 
-        // Items are already encoded in ItemTemplate::render
-        let n1 = items.len() as isize - "${items}".len() as isize;
+        let uses = |specifier| self.substitutions.iter().any(|s| s.specifier == specifier);
+        let items_by_channel = match uses(ItemsByChannel) {
+            true => render_items_by_channel(content, item_template, group_template),
+            false => String::new(),
+        };
+        let items_by_day = match uses(ItemsByDay) {
+            true => render_items_by_day(content, item_template, group_template),
+            false => String::new(),
+        };
+        let stylesheet = match uses(Stylesheet) {
+            true => format!("<style>\n{}\n</style>", STYLESHEET.trim_end()),
+            false => String::new(),
+        };
 
-        let channel_count = content
-            .iter()
-            .map(|item| &item.channel_url)
-            .collect::<HashSet<_>>()
-            .len()
-            .to_string();
+        let channel_count = data::channel_count(content).to_string();
 
         let (item_count, date, time, timestamp) = (
             content.len().to_string(),
@@ -220,96 +310,356 @@ impl Template for PageTemplate {
             chrono::Utc::now().format("%H:%M:%S").to_string(),
             chrono::Utc::now().timestamp().to_string(),
         );
+        // In local time (respecting `TZ`), unlike date/time above
+        let generated_at = chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false);
+        let (prev_page, next_page) = (
+            context.prev_page.unwrap_or_default(),
+            context.next_page.unwrap_or_default(),
+        );
 
-        use PageFormatSpecifier::*;
-        let (item_count_encoded, n2) = encode_specifier_with_size(&item_count, ItemCount);
-        let (channel_count_encoded, n3) = encode_specifier_with_size(&channel_count, ChannelCount);
-        let (date_encoded, n4) = encode_specifier_with_size(&date, Date);
-        let (time_encoded, n5) = encode_specifier_with_size(&time, Time);
-        let (timestamp_encoded, n6) = encode_specifier_with_size(&timestamp, Timestamp);
-
-        for subst in &self.substitutions {
-            size += match subst.specifier {
-                Items => n1,
-                ItemCount => n2,
-                ChannelCount => n3,
-                Date => n4,
-                Time => n5,
-                Timestamp => n6,
-            };
-        }
+        let encoded = self
+            .substitutions
+            .iter()
+            .map(|subst| {
+                let value = match subst.specifier {
+                    // Items are already encoded in ItemTemplate::render
+                    Items => return Cow::Borrowed(items.as_str()),
+                    ItemsByChannel => return Cow::Borrowed(items_by_channel.as_str()),
+                    ItemsByDay => return Cow::Borrowed(items_by_day.as_str()),
+                    // The stylesheet is trusted, so it is inserted verbatim
+                    Stylesheet => return Cow::Borrowed(stylesheet.as_str()),
+                    ItemCount => &item_count,
+                    ChannelCount => &channel_count,
+                    Date => &date,
+                    Time => &time,
+                    Timestamp => &timestamp,
+                    PrevPage => prev_page,
+                    NextPage => next_page,
+                    Version => NOOS_VERSION,
+                    BuildInfo => NOOS_BUILD,
+                    GeneratedAt => &generated_at,
+                };
+                Cow::Owned(encode_safe(&subst.truncate(value)).into_owned())
+            })
+            .collect::<Vec<_>>();
+
+        render_substitutions(&self.template, &self.substitutions, &encoded, &self.custom)
+    }
 
-        // Now do the actual rendering with substitutions.
-        let mut rendered = String::with_capacity(size as usize);
-
-        // Build the final string
-        let mut last_pos = 0;
-        for subst in &self.substitutions {
-            let (start, end) = (subst.start, subst.end);
-            let encoded = match subst.specifier {
-                Items => &items.clone().into(),
-                ItemCount => &item_count_encoded,
-                ChannelCount => &channel_count_encoded,
-                Date => &date_encoded,
-                Time => &time_encoded,
-                Timestamp => &timestamp_encoded,
-            };
+    fn validate(&self) -> Result<(), Vec<TemplateWarning>> {
+        validate_specifiers::<PageFormatSpecifier>(&self.template)
+    }
+}
 
-            rendered.push_str(&self.template[last_pos..start]);
-            rendered.push_str(encoded);
-            last_pos = end;
+/// Render the items grouped by channel (in order of first appearance),
+/// each group preceded by its header, see `GroupTemplate`
+fn render_items_by_channel(
+    content: &[TimelineItem],
+    item_template: &ItemTemplate,
+    group_template: &GroupTemplate,
+) -> String {
+    let mut groups: Vec<(&str, Vec<&TimelineItem>)> = Vec::new();
+    for item in content {
+        match groups.iter_mut().find(|(url, _)| *url == item.channel_url) {
+            Some((_, items)) => items.push(item),
+            None => groups.push((&item.channel_url, vec![item])),
         }
-        rendered.push_str(&self.template[last_pos..]);
-
-        rendered
     }
+
+    groups
+        .into_iter()
+        .map(|(url, items)| {
+            let header = group_template.render(&GroupHeader {
+                title: &items[0].channel_title,
+                link: url,
+                item_count: items.len(),
+            });
+            let items = items.into_iter().map(|item| item_template.render(item));
+            std::iter::once(header).chain(items).collect::<String>()
+        })
+        .collect()
+}
+
+/// Render the items grouped by local calendar day, each group preceded by its header
+/// (titled `Today`, `Yesterday` or `YYYY-MM-DD`), see `GroupTemplate`
+/// NOTE: Expects the timeline to be sorted newest-first, see `data::add_timeline_items`
+fn render_items_by_day(
+    content: &[TimelineItem],
+    item_template: &ItemTemplate,
+    group_template: &GroupTemplate,
+) -> String {
+    let local_day = |timestamp| {
+        chrono::DateTime::from_timestamp(timestamp, 0)
+            .unwrap_or_default()
+            .with_timezone(&chrono::Local)
+            .date_naive()
+    };
+    let today = chrono::Local::now().date_naive();
+
+    content
+        .chunk_by(|a, b| local_day(a.timestamp) == local_day(b.timestamp))
+        .map(|items| {
+            let day = local_day(items[0].timestamp);
+            let title = match (today - day).num_days() {
+                0 => "Today".to_string(),
+                1 => "Yesterday".to_string(),
+                _ => day.format("%Y-%m-%d").to_string(),
+            };
+            let header = group_template.render(&GroupHeader {
+                title: &title,
+                link: "",
+                item_count: items.len(),
+            });
+            let items = items.iter().map(|item| item_template.render(item));
+            std::iter::once(header).chain(items).collect::<String>()
+        })
+        .collect()
 }
 
-/// Find the positions of all occurrences of a format specifier in a template.
+/// Find all occurrences of a format specifier in a template.
 /// Format specifiers are of the form `${specifier}`,
 /// and can be escaped (ignored) with a leading backslash `\`.
-fn find_format_specifiers<F>(template: &str, specifier: F) -> Vec<(usize, usize)>
+///
+/// An optional truncation limit can be given as `${specifier:N}`,
+/// which limits the substituted text to `N` characters.
+fn find_format_specifiers<F>(template: &str, specifier: F) -> Vec<Substitution<F>>
 where
     F: FormatSpecifier,
 {
     // TODO: Reconsider the format specifier escaping logic
     // TODO: Parse all specifiers in one pass/regex for efficiency
-    let re = format!(r"(?:^|[^\\])\$\{{{specifier}\}}");
+    let re = format!(r"(?:^|[^\\])(\$\{{{specifier}(?::(\d+))?\}})");
     let re = Regex::new(&re).unwrap();
 
-    let specifier = specifier.to_string();
-    let mut positions = Vec::new();
+    let mut substitutions = Vec::new();
 
-    for m in re.find_iter(template) {
-        let start = if m.start() == 0 { 0 } else { m.start() + 1 }; // account for leading non-backslash char
+    for caps in re.captures_iter(template) {
+        let m = caps.get(1).unwrap(); // excludes the leading non-backslash char
+        let start = m.start();
         // Extra safety: ignore if escaped
         if start > 0 && template.as_bytes()[start.saturating_sub(1)] == b'\\' {
             debug!("Format specifier '${{{specifier}}}' is escaped, ignoring");
             continue;
         }
-        let end = start + specifier.len() + "${}".len();
+        let end = m.end();
+        let limit = caps.get(2).and_then(|n| n.as_str().parse().ok());
         debug!("Found format specifier '${{{specifier}}}' at position: ({start:?}-{end:?})");
-        positions.push((start, end));
+        substitutions.push(Substitution {
+            start,
+            end,
+            specifier,
+            limit,
+        });
     }
 
-    if positions.is_empty() {
+    if substitutions.is_empty() {
         debug!("Format specifier '${{{specifier}}}' not found in template");
     }
 
-    positions
+    substitutions
 }
 
-/// Helper to get html encoded string (Cow) and its size for a given specifier.
-fn encode_specifier_with_size<'a, F: FormatSpecifier>(
-    s: &'a str,
-    specifier: F,
-) -> (Cow<'a, str>, isize) {
-    let encoded = encode_safe(s);
-    let n = encoded.len() as isize;
-    (
-        encoded,
-        n - "${}".len() as isize - specifier.to_string().len() as isize,
-    )
+/// The name of user-defined format specifiers `${custom:KEY}`, see `init_template_vars`
+const CUSTOM_SPECIFIER: &str = "custom";
+
+/// The global user-defined template variables, see `init_template_vars`
+static TEMPLATE_VARS: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Initialize the global user-defined template variables once
+/// They are available in all templates as `${custom:KEY}`, e.g. for a site name or author
+/// Returns: `Err(vars)` if already initialized, otherwise `Ok(())`
+pub fn init_template_vars(vars: HashMap<String, String>) -> Result<(), HashMap<String, String>> {
+    TEMPLATE_VARS.set(vars)
+}
+
+/// Get the value of a user-defined template variable, if it exists
+fn template_var(key: &str) -> Option<&'static str> {
+    TEMPLATE_VARS.get()?.get(key).map(String::as_str)
+}
+
+/// A position of a user-defined format specifier `${custom:KEY}` in a template string
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CustomSubstitution {
+    start: usize,
+    end: usize,
+    key: String,
+}
+
+impl CustomSubstitution {
+    /// Get the (encoded) value of this substitution, or an empty string for unknown keys
+    fn resolve(&self) -> Cow<'static, str> {
+        encode_safe(template_var(&self.key).unwrap_or_default())
+    }
+}
+
+/// Find all occurrences of user-defined format specifiers `${custom:KEY}` in a template
+/// Unknown keys are substituted with an empty string, with a warning
+fn find_custom_specifiers(template: &str) -> Vec<CustomSubstitution> {
+    let re = format!(r"\$\{{{CUSTOM_SPECIFIER}:([^}}\n]+)\}}");
+    let re = Regex::new(&re).unwrap();
+
+    re.captures_iter(template)
+        .map(|caps| (caps.get(0).unwrap(), caps[1].to_string()))
+        // Ignore escaped specifiers, without consuming the preceding char (unlike
+        // `find_format_specifiers`), so adjacent specifiers are found as well
+        .filter(|(m, _)| m.start() == 0 || template.as_bytes()[m.start() - 1] != b'\\')
+        .map(|(m, key)| {
+            if template_var(&key).is_none() {
+                warn!("Unknown template variable '{key}', substituting nothing");
+            }
+            CustomSubstitution {
+                start: m.start(),
+                end: m.end(),
+                key,
+            }
+        })
+        .collect()
+}
+
+/// Check all (unescaped) `${...}` sequences of a template against the known format specifiers
+/// Reports unknown specifiers, invalid truncation limits and unterminated sequences
+fn validate_specifiers<F>(template: &str) -> Result<(), Vec<TemplateWarning>>
+where
+    F: FormatSpecifier,
+{
+    let known = F::ALL.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    let line_of = |pos: usize| template[..pos].matches('\n').count() + 1;
+
+    let mut warnings = Vec::new();
+    for (start, _) in template.match_indices("${") {
+        if start > 0 && template.as_bytes()[start - 1] == b'\\' {
+            continue;
+        }
+        let line = line_of(start);
+
+        let rest = &template[start + 2..];
+        let Some(len) = rest
+            .find(['}', '\n'])
+            .filter(|&i| rest[i..].starts_with('}'))
+        else {
+            warnings.push(TemplateWarning::Unterminated { line });
+            continue;
+        };
+
+        let (name, limit) = match rest[..len].split_once(':') {
+            Some((name, limit)) => (name, Some(limit)),
+            None => (&rest[..len], None),
+        };
+        if name == CUSTOM_SPECIFIER {
+            let key = limit.unwrap_or_default();
+            if template_var(key).is_none() {
+                let key = key.to_string();
+                warnings.push(TemplateWarning::UnknownVariable { key, line });
+            }
+        } else if !known.iter().any(|k| k == name) {
+            let name = name.to_string();
+            warnings.push(TemplateWarning::UnknownSpecifier { name, line });
+        } else if let Some(limit) = limit
+            && limit.parse::<usize>().is_err()
+        {
+            let limit = limit.to_string();
+            warnings.push(TemplateWarning::InvalidLimit { limit, line });
+        }
+    }
+
+    match warnings.is_empty() {
+        true => Ok(()),
+        false => Err(warnings),
+    }
+}
+
+/// A problem found in a template, see `Template::validate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateWarning {
+    /// A `${...}` sequence naming no known format specifier
+    UnknownSpecifier { name: String, line: usize },
+    /// A truncation limit `${specifier:N}` where `N` isn't a number
+    InvalidLimit { limit: String, line: usize },
+    /// A `${custom:KEY}` sequence naming no user-defined template variable
+    UnknownVariable { key: String, line: usize },
+    /// A `${` without a closing `}` on the same line
+    Unterminated { line: usize },
+}
+
+impl std::fmt::Display for TemplateWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use TemplateWarning::*;
+        match self {
+            UnknownSpecifier { name, line } => {
+                write!(f, "line {line}: unknown format specifier '${{{name}}}'")
+            }
+            InvalidLimit { limit, line } => {
+                write!(f, "line {line}: invalid truncation limit '{limit}'")
+            }
+            UnknownVariable { key, line } => {
+                write!(
+                    f,
+                    "line {line}: unknown template variable '{key}' (see `template_vars`)"
+                )
+            }
+            Unterminated { line } => write!(f, "line {line}: unterminated '${{' (missing '}}')"),
+        }
+    }
+}
+
+/// Build the rendered string by replacing each substitution in the template
+/// with its (already encoded) value. `encoded` is parallel to `substitutions`.
+/// User-defined `custom` substitutions are resolved here, see `CustomSubstitution::resolve`
+fn render_substitutions<F, S>(
+    template: &str,
+    substitutions: &[Substitution<F>],
+    encoded: &[S],
+    custom: &[CustomSubstitution],
+) -> String
+where
+    F: FormatSpecifier,
+    S: AsRef<str>,
+{
+    let custom_encoded = custom
+        .iter()
+        .map(CustomSubstitution::resolve)
+        .collect::<Vec<_>>();
+    let mut spans = substitutions
+        .iter()
+        .zip(encoded)
+        .map(|(subst, s)| (subst.start, subst.end, s.as_ref()))
+        .chain(
+            custom
+                .iter()
+                .zip(&custom_encoded)
+                .map(|(subst, s)| (subst.start, subst.end, s.as_ref())),
+        )
+        .collect::<Vec<_>>();
+    spans.sort_by_key(|&(start, _, _)| start);
+
+    // Made efficient by using size calculations.
+    // Start with template size, then for each substitution,
+    // add the size of the encoded string and subtract
+    // the size of the format specifier.
+    let size = spans.iter().fold(template.len(), |size, (start, end, s)| {
+        size + s.len() - (end - start)
+    });
+
+    let mut rendered = String::with_capacity(size);
+
+    let mut last_pos = 0;
+    for (start, end, s) in spans {
+        rendered.push_str(&template[last_pos..start]);
+        rendered.push_str(s);
+        last_pos = end;
+    }
+    rendered.push_str(&template[last_pos..]);
+
+    rendered
+}
+
+/// Truncate a string to at most `limit` characters (not bytes),
+/// appending an ellipsis if anything was cut off.
+fn truncate_chars(s: &str, limit: usize) -> Cow<'_, str> {
+    match s.char_indices().nth(limit) {
+        Some((i, _)) => format!("{}…", &s[..i]).into(),
+        None => s.into(),
+    }
 }
 
 pub trait Template: Default {
@@ -328,14 +678,55 @@ pub trait Template: Default {
 
     /// Render the template with given dependencies
     fn render<'a>(&self, content: Self::Deps<'a>) -> String;
+
+    /// Check the template for unknown or malformed format specifiers
+    /// NOTE: Escaped sequences `\${...}` are ignored
+    fn validate(&self) -> Result<(), Vec<TemplateWarning>>;
 }
 
+/// How the value of a format specifier is made safe for insertion into html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    /// Escape all html, inserting the value as plain text
+    Escape,
+    /// Keep a restricted set of html tags, see `HTML_SANITIZER`
+    Sanitize,
+}
+
+/// The html sanitizer used for `Encoding::Sanitize`
+/// Only allows links, emphasis, lists and images (and basic paragraphs)
+static HTML_SANITIZER: LazyLock<ammonia::Builder<'static>> = LazyLock::new(|| {
+    let mut builder = ammonia::Builder::empty();
+    builder
+        .add_tags([
+            "a", "b", "strong", "i", "em", "ul", "ol", "li", "img", "p", "br",
+        ])
+        .add_tag_attributes("a", ["href", "title"])
+        .add_tag_attributes("img", ["src", "alt", "title"])
+        .add_url_schemes(["http", "https", "mailto"])
+        .link_rel(Some("noopener noreferrer"));
+    builder
+});
+
 /// A position of a format specifier in a template string.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Substitution<F: FormatSpecifier> {
     start: usize,
     end: usize,
     specifier: F,
+    /// Optional maximum number of characters to substitute, see `${specifier:N}`
+    limit: Option<usize>,
+}
+
+impl<F: FormatSpecifier> Substitution<F> {
+    /// Apply the truncation limit of this substitution (if any) to a value.
+    /// NOTE: Must be applied before html encoding, to not split escape sequences.
+    fn truncate<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        match self.limit {
+            Some(limit) => truncate_chars(value, limit),
+            None => value.into(),
+        }
+    }
 }
 
 /// An enum containing all well-defined
@@ -344,12 +735,27 @@ struct Substitution<F: FormatSpecifier> {
 pub enum ItemFormatSpecifier {
     Title,
     Description,
+    DescriptionText,
     Source,
     Link,
     Date,
     Time,
     Timestamp,
     ChannelLink,
+    Anchor,
+    Tags,
+    RelativeTime,
+    PubDateTime,
+    Favicon,
+    Thumbnail,
+    MediaContent,
+    MediaThumbnail,
+    MediaDescription,
+    MediaCredit,
+    DcCreator,
+    DcSubject,
+    DcPublisher,
+    Content,
     // TODO: Add item format specifier for all RSS item fields including media (images)
     //       see https://www.rssboard.org/rss-specification#hrelementsOfLtitemgt
 }
@@ -359,12 +765,28 @@ pub enum ItemFormatSpecifier {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PageFormatSpecifier {
     Items,
+    ItemsByChannel,
+    ItemsByDay,
     ItemCount,
     ChannelCount,
     Date,
     Time,
     Timestamp,
-    // TODO: Add page format specifier for noos metadata (version/build)
+    PrevPage,
+    NextPage,
+    Version,
+    BuildInfo,
+    GeneratedAt,
+    Stylesheet,
+}
+
+/// An enum containing all well-defined
+/// format specifiers for group header templates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupFormatSpecifier {
+    Title,
+    Link,
+    ItemCount,
 }
 
 impl std::fmt::Display for ItemFormatSpecifier {
@@ -373,69 +795,262 @@ impl std::fmt::Display for ItemFormatSpecifier {
         let s = match self {
             Title => "title",
             Description => "description",
+            DescriptionText => "description_text",
             Source => "source",
             Link => "link",
             Date => "date",
             Time => "time",
             Timestamp => "timestamp",
             ChannelLink => "channel_link",
+            Anchor => "anchor",
+            Tags => "tags",
+            RelativeTime => "relative_time",
+            PubDateTime => "pub_datetime",
+            Favicon => "favicon",
+            Thumbnail => "thumbnail",
+            MediaContent => "media_content",
+            MediaThumbnail => "media_thumbnail",
+            MediaDescription => "media_description",
+            MediaCredit => "media_credit",
+            DcCreator => "dc_creator",
+            DcSubject => "dc_subject",
+            DcPublisher => "dc_publisher",
+            Content => "content",
         };
         write!(f, "{s}")
     }
 }
 
+impl ItemFormatSpecifier {
+    /// Get the encoding to apply to the value of this specifier
+    fn encoding(&self, sanitize_html: bool) -> Encoding {
+        match self {
+            ItemFormatSpecifier::Description | ItemFormatSpecifier::Content if sanitize_html => {
+                Encoding::Sanitize
+            }
+            _ => Encoding::Escape,
+        }
+    }
+
+    /// Get the (unencoded) value of this specifier for an item rendered by `template`
+    fn resolve(&self, item: &TimelineItem, template: &ItemTemplate) -> String {
+        use ItemFormatSpecifier::*;
+        match self {
+            Title => item.title(),
+            Description => item.description(),
+            DescriptionText => item.description_text(),
+            Source => item.source(),
+            Link => item.link(),
+            Date => item.date(&template.date_format),
+            Time => item.time(&template.time_format),
+            Timestamp => item.timestamp.to_string(),
+            ChannelLink => item.channel_url.clone(),
+            Anchor => item.anchor(),
+            Tags => item.tags(),
+            RelativeTime => item.relative_time(),
+            PubDateTime => item.pub_datetime(),
+            Favicon => item.favicon_url(),
+            Thumbnail => item.thumbnail_url(),
+            MediaContent => item.media_content_url(),
+            MediaThumbnail => item.media_thumbnail_url(),
+            MediaDescription => item.media_description(),
+            MediaCredit => item.media_credit(),
+            DcCreator => item.dc_creator(),
+            DcSubject => item.dc_subject(),
+            DcPublisher => item.dc_publisher(),
+            Content => item.content(),
+        }
+    }
+}
+
 impl std::fmt::Display for PageFormatSpecifier {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use PageFormatSpecifier::*;
         let s = match self {
             Items => "items",
+            ItemsByChannel => "items_by_channel",
+            ItemsByDay => "items_by_day",
             ItemCount => "item_count",
             ChannelCount => "channel_count",
             Date => "date",
             Time => "time",
             Timestamp => "timestamp",
+            PrevPage => "prev_page",
+            NextPage => "next_page",
+            Version => "noos_version",
+            BuildInfo => "noos_build",
+            GeneratedAt => "generated_at",
+            Stylesheet => "stylesheet",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl std::fmt::Display for GroupFormatSpecifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use GroupFormatSpecifier::*;
+        let s = match self {
+            Title => "title",
+            Link => "link",
+            ItemCount => "item_count",
         };
         write!(f, "{s}")
     }
 }
 
-pub trait FormatSpecifier: std::fmt::Display {}
-impl FormatSpecifier for ItemFormatSpecifier {}
-impl FormatSpecifier for PageFormatSpecifier {}
+pub trait FormatSpecifier: std::fmt::Display + Copy + 'static {
+    /// All format specifiers of this kind, in parsing order
+    const ALL: &'static [Self];
+}
+
+impl FormatSpecifier for ItemFormatSpecifier {
+    const ALL: &'static [Self] = {
+        use ItemFormatSpecifier::*;
+        &[
+            Title,
+            Description,
+            DescriptionText,
+            Source,
+            Link,
+            Date,
+            Time,
+            Timestamp,
+            ChannelLink,
+            Anchor,
+            Tags,
+            RelativeTime,
+            PubDateTime,
+            Favicon,
+            Thumbnail,
+            MediaContent,
+            MediaThumbnail,
+            MediaDescription,
+            MediaCredit,
+            DcCreator,
+            DcSubject,
+            DcPublisher,
+            Content,
+        ]
+    };
+}
+
+impl FormatSpecifier for PageFormatSpecifier {
+    const ALL: &'static [Self] = {
+        use PageFormatSpecifier::*;
+        &[
+            Items,
+            ItemsByChannel,
+            ItemsByDay,
+            ItemCount,
+            ChannelCount,
+            Date,
+            Time,
+            Timestamp,
+            PrevPage,
+            NextPage,
+            Version,
+            BuildInfo,
+            GeneratedAt,
+            Stylesheet,
+        ]
+    };
+}
 
-// TODO: use serde and build.rs to pre-parse default templates into baked-in binary dump
+impl FormatSpecifier for GroupFormatSpecifier {
+    const ALL: &'static [Self] = {
+        use GroupFormatSpecifier::*;
+        &[Title, Link, ItemCount]
+    };
+}
 
 impl Default for ItemTemplate {
-    /// Load and parse the baked-in default item template
-    /// NOTE: parsing at runtime is bad, but parsing at comptime is very tedious in rust
+    /// Load the baked-in default item template, pre-parsed by `build.rs`
     fn default() -> Self {
         let template = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/item.html"));
-        Self::parse(template)
+        let substitutions = include_bytes!(concat!(env!("OUT_DIR"), "/item_substitutions.bin"));
+        Self {
+            template: template.to_string(),
+            substitutions: precompiled_substitutions(substitutions),
+            custom: Vec::new(),
+            sanitize_html: false,
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            time_format: DEFAULT_TIME_FORMAT.to_string(),
+        }
     }
 }
 
 impl Default for PageTemplate {
-    /// Load and parse the baked-in default page template
-    /// NOTE: parsing at runtime is bad, but parsing at comptime is very tedious in rust
+    /// Load the baked-in default page template, pre-parsed by `build.rs`
     fn default() -> Self {
         let template = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/page.html"));
-        Self::parse(template)
+        let substitutions = include_bytes!(concat!(env!("OUT_DIR"), "/page_substitutions.bin"));
+        Self {
+            template: template.to_string(),
+            substitutions: precompiled_substitutions(substitutions),
+            custom: Vec::new(),
+        }
+    }
+}
+
+/// A substitution as `(start, end, specifier name, truncation limit)`, see `build.rs`
+type RawSubstitution = (usize, usize, String, Option<usize>);
+
+impl Default for GroupTemplate {
+    /// Load the baked-in default group header template, pre-parsed by `build.rs`
+    fn default() -> Self {
+        let template = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/group.html"));
+        let substitutions = include_bytes!(concat!(env!("OUT_DIR"), "/group_substitutions.bin"));
+        Self {
+            template: template.to_string(),
+            substitutions: precompiled_substitutions(substitutions),
+            custom: Vec::new(),
+        }
     }
 }
 
+/// Decode the substitutions of a default template, as precomputed by `build.rs`
+/// Sequences naming no known format specifier are dropped
+fn precompiled_substitutions<F: FormatSpecifier>(bytes: &[u8]) -> Vec<Substitution<F>> {
+    let (raw, _): (Vec<RawSubstitution>, _) =
+        bincode::decode_from_slice(bytes, bincode::config::standard())
+            .expect("substitutions are encoded by build.rs");
+
+    raw.into_iter()
+        .filter_map(|(start, end, name, limit)| {
+            let specifier = *F::ALL.iter().find(|s| s.to_string() == name)?;
+            Some(Substitution {
+                start,
+                end,
+                specifier,
+                limit,
+            })
+        })
+        .collect()
+}
+
+/// File names of the user-defined page, item and group templates in the config directory
+pub const PAGE_TEMPLATE_FILE: &str = "page_template.html";
+pub const ITEM_TEMPLATE_FILE: &str = "item_template.html";
+pub const GROUP_TEMPLATE_FILE: &str = "group_template.html";
+
+/// File name of the user-defined stylesheet in the config directory, see `STYLESHEET`
+pub const STYLESHEET_FILE: &str = "style.css";
+
 /// Load user-defined templates from config directory,
 /// or fall back to the built-in defaults if not found.
 pub fn load_templates_or_default<P>(
     page_template_path: Option<P>,
     item_template_path: Option<P>,
-) -> (PageTemplate, ItemTemplate)
+    group_template_path: Option<P>,
+) -> (PageTemplate, ItemTemplate, GroupTemplate)
 where
     P: AsRef<Path>,
 {
     info!("Parsing HTML templates...");
     let ts = (
-        load_template(page_template_path, "page_template.html"),
-        load_template(item_template_path, "item_template.html"),
+        load_template(page_template_path, PAGE_TEMPLATE_FILE),
+        load_template(item_template_path, ITEM_TEMPLATE_FILE),
+        load_template(group_template_path, GROUP_TEMPLATE_FILE),
     );
     info!("Finished parsing HTML templates!");
 
@@ -473,17 +1088,55 @@ where
     }
 }
 
-/// Get the path of a file in the config directory `$config_dir/noos/$filename`
+/// Get the path of a file in the config directory `$config_base/$filename`
 /// Returns None if the config dir or the file can't be found
-/// See `dirs::config_dir` for more info on where this is located
+/// See `data::config_base` for more info on where this is located
 fn get_user_config_file<P: AsRef<Path>>(filename: P) -> Option<PathBuf> {
-    let file: PathBuf = dirs::config_dir()?
-        .join(env!("CARGO_BIN_NAME"))
-        .join(filename);
+    let file: PathBuf = data::config_base()?.join(filename);
 
     file.exists().then_some(file)
 }
 
+/// Validate all templates, logging all warnings
+/// Returns: whether all templates are free of warnings
+pub fn check_templates(
+    page_template: &PageTemplate,
+    item_template: &ItemTemplate,
+    group_template: &GroupTemplate,
+) -> bool {
+    let mut ok = true;
+    for (name, result) in [
+        ("page", page_template.validate()),
+        ("item", item_template.validate()),
+        ("group", group_template.validate()),
+    ] {
+        match result {
+            Ok(()) => info!("The {name} template is valid."),
+            Err(warnings) => {
+                ok = false;
+                for warning in warnings {
+                    warn!("The {name} template has a problem at {warning}");
+                }
+            }
+        }
+    }
+    ok
+}
+
+/// Get the path of the `n`-th page (starting at 1) when paginating,
+/// e.g. `noos.html`, `noos-2.html`, `noos-3.html`, ...
+pub fn page_file_path(base: &Path, n: usize) -> PathBuf {
+    if n <= 1 {
+        return base.to_path_buf();
+    }
+    let stem = base.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match base.extension() {
+        Some(ext) => format!("{stem}-{n}.{}", ext.to_string_lossy()),
+        None => format!("{stem}-{n}"),
+    };
+    base.with_file_name(file_name)
+}
+
 /// Dump the generated HTML to a file, with logging output.
 /// Exits on failure.
 pub fn dump_html_to_file<P: AsRef<Path>>(html: &str, path: P) {
@@ -493,7 +1146,7 @@ pub fn dump_html_to_file<P: AsRef<Path>>(html: &str, path: P) {
     match std::fs::write(path, html) {
         Err(e) => {
             error!("Fatal: Failed to write output HTML file: {e}");
-            std::process::exit(1);
+            logger::exit(1);
         }
         Ok(_) => info!("Successfully dumped output HTML file!"),
     }
@@ -501,3 +1154,77 @@ pub fn dump_html_to_file<P: AsRef<Path>>(html: &str, path: P) {
 
 // TODO: Fix times using UTC instead of local time (everywhere)
 //       Use UTC internally, then convert to local for user facing dates/times
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An item with the given title, from an example channel
+    fn item(title: &str) -> TimelineItem {
+        crate::logger::init_for_tests();
+        TimelineItem {
+            item: rss::ItemBuilder::default().title(title.to_string()).build(),
+            channel_title: "Example".into(),
+            channel_url: "https://example.com/feed".into(),
+            channel_tags: Vec::new(),
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn keeps_text_around_specifiers() {
+        let item = item("Title");
+        assert_eq!(ItemTemplate::parse("${title}").render(&item), "Title");
+        assert_eq!(ItemTemplate::parse("x${title}").render(&item), "xTitle");
+        assert_eq!(
+            ItemTemplate::parse("<b>${title}</b>").render(&item),
+            "<b>Title</b>"
+        );
+        assert_eq!(
+            ItemTemplate::parse(r"x\${title}").render(&item),
+            r"x\${title}"
+        );
+    }
+
+    #[test]
+    fn truncates_to_limit_before_escaping() {
+        let item = item("Fish & Chips");
+        assert_eq!(ItemTemplate::parse("${title:4}").render(&item), "Fish…");
+        assert_eq!(
+            ItemTemplate::parse("${title:6}").render(&item),
+            "Fish &amp;…"
+        );
+        assert_eq!(
+            ItemTemplate::parse("${title:12}").render(&item),
+            "Fish &amp; Chips"
+        );
+        assert_eq!(
+            ItemTemplate::parse("${title:2}").render(&self::item("äöü")),
+            "äö…"
+        );
+    }
+
+    #[test]
+    fn counts_items_and_channels() {
+        let other_channel = TimelineItem {
+            channel_url: "https://example.org/rss".into(),
+            ..item("C")
+        };
+        let timeline = [item("A"), item("B"), other_channel];
+        assert_eq!(data::channel_count(&timeline), 2);
+
+        let page = PageTemplate::parse("${item_count} items from ${channel_count} channels");
+        let (item_template, group_template) = Default::default();
+        let render = |items| {
+            page.render(&PageContext {
+                items,
+                item_template: &item_template,
+                group_template: &group_template,
+                prev_page: None,
+                next_page: None,
+            })
+        };
+        assert_eq!(render(&timeline), "3 items from 2 channels");
+        assert_eq!(render(&[]), "0 items from 0 channels");
+    }
+}
@@ -1,18 +1,68 @@
 // A static logger used throughout the application
 // - Supports log levels: error, warn, info, debug (ascending verbosity)
-// - Supports logging to stderr and optionally also a log file
+// - Supports logging to stderr and optionally also a log file (with size-based rotation)
 
-use std::sync::{LazyLock, OnceLock};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        LazyLock, Mutex, OnceLock,
+        atomic::{AtomicU8, Ordering},
+    },
+};
 
 /// A configuration for the static logger
 /// See `init` and `log` to use the logger
 #[derive(Debug)]
 pub struct LoggerConfig {
-    /// Log file
-    pub file: Option<std::fs::File>,
+    /// Log file (buffered, flushed on warnings and errors, see `flush`)
+    pub file: Option<Mutex<LogFile>>,
 
     /// Specified verbosity
     pub minimum_level: LogLevel,
+
+    /// Format of logged lines
+    pub format: LogFormat,
+
+    /// `chrono` format of the timestamp of logged lines (in text format)
+    /// See `validate_timestamp_format`
+    pub timestamp_format: String,
+
+    /// Whether logged lines start with a timestamp
+    pub show_timestamp: bool,
+
+    /// Whether logged lines include their level, like `[info]`
+    pub show_level: bool,
+}
+
+/// The default `chrono` format of the timestamp of logged lines
+pub const DEFAULT_TIMESTAMP_FORMAT: &str = "[%Y-%m-%d %H:%M:%S]";
+
+/// Check a `chrono` format string for invalid or unsupported specifiers,
+/// which would otherwise make formatting fail (and panic) when logging
+pub fn validate_timestamp_format(format: &str) -> Result<(), String> {
+    use chrono::format::{Item, StrftimeItems};
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return Err(format!("invalid timestamp format '{format}'"));
+    }
+
+    // Specifiers may still be unsupported for the (timezone-aware) local time
+    use std::fmt::Write;
+    let mut formatted = String::new();
+    write!(formatted, "{}", chrono::Local::now().format(format))
+        .map_err(|_| format!("unsupported timestamp format '{format}'"))
+}
+
+/// Formats of logged lines
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines, colorized on terminals
+    #[default]
+    Text,
+    /// One JSON object per line, like `{"ts":"...","level":"info","msg":"..."}`
+    Json,
 }
 
 /// The global logger instance
@@ -77,16 +127,221 @@ impl std::str::FromStr for LogLevel {
     }
 }
 
+impl<'de> serde::Deserialize<'de> for LogLevel {
+    /// Deserialize a log level from a string or an integer, see `from_str`
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Int(u8),
+            Str(String),
+        }
+
+        let s = match Repr::deserialize(deserializer)? {
+            Repr::Int(n) => n.to_string(),
+            Repr::Str(s) => s,
+        };
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A buffered log file, rotated once it exceeds a maximum size (see `rotate`)
+#[derive(Debug)]
+pub struct LogFile {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    /// Current size of the file (including buffered lines)
+    size: u64,
+    /// Maximum size before rotating, or `None` to never rotate
+    max_size: Option<u64>,
+    /// Number of rotated files kept (`noos.log.1`, `noos.log.2`, ...)
+    max_files: usize,
+}
+
+impl LogFile {
+    /// Open a log file for appending, creating it if it doesn't exist
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        max_size: Option<u64>,
+        max_files: usize,
+    ) -> std::io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            writer: BufWriter::new(file),
+            path,
+            size,
+            max_size,
+            max_files,
+        })
+    }
+
+    /// Write a line, first rotating the file if the line would exceed the maximum size
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if let Some(max_size) = self.max_size
+            && self.size > 0
+            && self.size + len > max_size
+        {
+            self.rotate()?;
+        }
+
+        writeln!(self.writer, "{line}")?;
+        self.size += len;
+        Ok(())
+    }
+
+    /// Move the file to `<path>.1` (and older rotated files up by one),
+    /// dropping the oldest, then continue with an empty file
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.writer.flush()?;
+
+        let rotated = |n: usize| {
+            let mut path = self.path.clone().into_os_string();
+            path.push(format!(".{n}"));
+            PathBuf::from(path)
+        };
+        if self.max_files > 0 {
+            let _ = std::fs::remove_file(rotated(self.max_files));
+            for n in (1..self.max_files).rev() {
+                if rotated(n).exists() {
+                    std::fs::rename(rotated(n), rotated(n + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, rotated(1))?;
+        }
+
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        self.writer = BufWriter::new(file);
+        self.size = 0;
+        Ok(())
+    }
+}
+
 /// Initialize the global logger once
 /// Returns: `Err(Logger)` if already initialized, otherwise `Ok(())`
-pub fn init<F>(file: F, minimum_level: LogLevel) -> Result<(), LoggerConfig>
+/// NOTE: Check the timestamp format first, see `validate_timestamp_format`
+pub fn init<F>(
+    file: F,
+    minimum_level: LogLevel,
+    format: LogFormat,
+    timestamp_format: String,
+    show_timestamp: bool,
+    show_level: bool,
+) -> Result<(), Box<LoggerConfig>>
 where
-    F: Into<Option<std::fs::File>>,
+    F: Into<Option<LogFile>>,
 {
-    LOGGER.set(LoggerConfig {
-        file: file.into(),
-        minimum_level,
-    })
+    LOGGER
+        .set(LoggerConfig {
+            file: file.into().map(Mutex::new),
+            minimum_level,
+            format,
+            timestamp_format,
+            show_timestamp,
+            show_level,
+        })
+        .map_err(Box::new)
+}
+
+/// Flush buffered messages to the log file, if any
+/// NOTE: Call before exiting, as buffered messages are lost otherwise
+pub fn flush() {
+    if let Some(file) = LOGGER.get().and_then(|logger| logger.file.as_ref()) {
+        let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+        file.writer.flush().expect("Failed to flush log file");
+    }
+}
+
+/// Flush the log file (see `flush`) and exit the process with the given status code
+/// NOTE: Use this instead of `std::process::exit`, so no buffered messages are lost
+pub fn exit(code: i32) -> ! {
+    flush();
+    std::process::exit(code)
+}
+
+/// Get the log file of tests, unique to the test process, see `init_for_tests`
+#[cfg(test)]
+pub fn test_log_path() -> PathBuf {
+    std::env::temp_dir().join(format!("noos-test-{}.log", std::process::id()))
+}
+
+/// Initialize the global logger for tests (unless already done)
+/// Messages from info up are also written to `test_log_path`, so tests can check them
+/// (call `flush` before reading it)
+#[cfg(test)]
+pub fn init_for_tests() {
+    if LOGGER.get().is_some() {
+        return;
+    }
+    let file = LogFile::open(test_log_path(), None, 0).expect("Failed to open test log file");
+    let _ = init(
+        file,
+        LogLevel::Info,
+        LogFormat::default(),
+        DEFAULT_TIMESTAMP_FORMAT.into(),
+        false,
+        true,
+    );
+}
+
+/// Format a logged message as a JSON line, see `LogFormat::Json`
+/// Timestamp and level are omitted as configured, see `LoggerConfig`
+pub fn json_line(
+    logger: &LoggerConfig,
+    ts: chrono::DateTime<chrono::Local>,
+    level: LogLevel,
+    msg: &str,
+) -> String {
+    #[derive(serde::Serialize)]
+    struct JsonLine<'a> {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        ts: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        level: Option<String>,
+        msg: &'a str,
+    }
+
+    let line = JsonLine {
+        ts: logger.show_timestamp.then(|| ts.to_rfc3339()),
+        level: logger.show_level.then(|| level.to_string().to_lowercase()),
+        msg,
+    };
+    serde_json::to_string(&line).expect("log lines are always serializable")
+}
+
+/// Format a logged message as a human readable line, see `LogFormat::Text`
+/// Timestamp and level prefix are optional, see `LoggerConfig`
+pub fn text_line(datetime: Option<String>, prefix: Option<String>, message: &str) -> String {
+    let head = [datetime, prefix].into_iter().flatten().collect::<Vec<_>>();
+    match head.is_empty() {
+        true => message.to_string(),
+        false => format!("{}  {message}", head.join(" ")),
+    }
+}
+
+/// Write a logged line to the log file (if any)
+/// Flushes right away for warnings and errors, see `flush`
+pub fn write_to_file(logger: &LoggerConfig, level: LogLevel, line: &str) {
+    if let Some(file) = &logger.file {
+        let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+        file.write_line(line).expect("Failed to write to log file");
+        if level >= LogLevel::Warn {
+            file.writer.flush().expect("Failed to flush log file");
+        }
+    }
 }
 
 /// A macro helper to generate color functions
@@ -105,7 +360,8 @@ color_fn!(blue, "\x1b[34m");
 color_fn!(magenta, "\x1b[35m");
 color_fn!(lightgray, "\x1b[37m");
 
-/// A global flag indicating whether to colorize output
+/// A global flag indicating whether to colorize output, detected from the environment
+/// NOTE: Use `colorize`, which respects the color choice (see `set_color_choice`)
 pub static COLORIZE: LazyLock<bool> = LazyLock::new(|| {
     use std::io::IsTerminal;
     let colorize = std::env::var_os("NO_COLOR").is_none() // NO_COLOR disables all colors
@@ -120,6 +376,39 @@ pub static COLORIZE: LazyLock<bool> = LazyLock::new(|| {
     }
 });
 
+/// Whether to colorize output, see `set_color_choice`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum ColorChoice {
+    /// Colorize output as detected by `COLORIZE`
+    #[default]
+    Auto = 0,
+    /// Always colorize output
+    Always = 1,
+    /// Never colorize output
+    Never = 2,
+}
+
+/// The global color choice (as `ColorChoice` discriminant)
+/// See `set_color_choice`
+static COLOR_CHOICE: AtomicU8 = AtomicU8::new(ColorChoice::Auto as u8);
+
+/// Override whether to colorize output, instead of detecting it via `COLORIZE`
+/// NOTE: Set this before logging anything, to keep output consistent
+pub fn set_color_choice(choice: ColorChoice) {
+    COLOR_CHOICE.store(choice as u8, Ordering::Relaxed);
+}
+
+/// Whether to colorize output, according to the color choice (see `set_color_choice`)
+pub fn colorize() -> bool {
+    match COLOR_CHOICE.load(Ordering::Relaxed) {
+        n if n == ColorChoice::Always as u8 => true,
+        n if n == ColorChoice::Never as u8 => false,
+        _ => *COLORIZE,
+    }
+}
+
 /// Log a message
 /// Note that the Logger must first be initialized via `init`
 #[macro_export]
@@ -137,38 +426,46 @@ macro_rules! log {
             }
 
             let message = format!($($arg)*);
-            let datetime = chrono::Local::now().format("[%Y-%m-%d %H:%M:%S]").to_string();
+            let now = chrono::Local::now();
 
-            let prefix = match $level {
+            // json lines are written as-is to stderr and file
+            if logger.format == LogFormat::Json {
+                let msg = json_line(logger, now, $level, &message);
+                eprintln!("{msg}");
+                write_to_file(logger, $level, &msg);
+                break;
+            }
+
+            let datetime = logger
+                .show_timestamp
+                .then(|| now.format(&logger.timestamp_format).to_string());
+
+            let prefix = logger.show_level.then_some(match $level {
                 LogLevel::Debug => "[debug]",
                 LogLevel::Info => "[info] ",
                 LogLevel::Warn => "[warn] ",
                 LogLevel::Error => "[error]",
-            };
+            });
 
-            let msg = format!("{datetime} {prefix}  {message}");
+            let msg = text_line(datetime.clone(), prefix.map(String::from), &message);
 
             // write to stderr (colorized if supported)
-            if *COLORIZE {
-                let prefix = match $level {
+            if colorize() {
+                let prefix = prefix.map(|prefix| match $level {
                     LogLevel::Debug => magenta(prefix),
                     LogLevel::Info => blue(prefix),
                     LogLevel::Warn => yellow(prefix),
                     LogLevel::Error => red(prefix),
-                };
-                let datetime = lightgray(&datetime);
-                let msg_colorized = format!("{datetime} {prefix}  {message}");
+                });
+                let datetime = datetime.map(|datetime| lightgray(&datetime));
+                let msg_colorized = text_line(datetime, prefix, &message);
                 eprintln!("{msg_colorized}");
             } else {
                 eprintln!("{msg}");
             }
 
             // write uncolorized to file
-            if let Some(file) = &logger.file {
-                use std::io::Write;
-                let mut file = file.try_clone().expect("Failed to clone log file handle");
-                writeln!(file, "{msg}").expect("Failed to write to log file");
-            }
+            write_to_file(logger, $level, &msg);
         }
     };
 }
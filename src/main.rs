@@ -1,12 +1,12 @@
-use std::path::Path;
-
-use clap::Parser;
+use std::time::Duration;
 
 mod cli;
 mod data;
 mod html;
 mod logger;
+mod output;
 mod serialize;
+mod server;
 
 pub use logger::LogLevel;
 
@@ -14,130 +14,912 @@ use html::Template;
 
 fn main() {
     // Arg-parsing and initialization
-    let mut args = cli::Args::parse();
+    let mut args = cli::parse();
     args = cli::validate(&args);
 
-    logger::init(None, args.verbosity).unwrap();
-    debug!("Parsed arguments: {args:?}");
+    logger::set_color_choice(args.color);
+    let log_file = args
+        .log_file
+        .as_ref()
+        .map(|path| logger::LogFile::open(path, args.log_max_size, args.log_max_files));
+    let (log_file, log_file_error) = match log_file {
+        Some(Err(e)) => (None, Some(e)),
+        log_file => (log_file.and_then(Result::ok), None),
+    };
+    logger::init(
+        log_file,
+        args.verbosity,
+        args.log_format,
+        args.log_timestamp_format.clone(),
+        !args.log_no_timestamp,
+        !args.log_no_level,
+    )
+    .unwrap();
+    if let Some(e) = log_file_error {
+        let path = args.log_file.as_ref().unwrap().display();
+        error!("Fatal: Failed to open log file '{path}': {e}");
+        logger::exit(1);
+    }
+    debug!(
+        "Parsed arguments: {}",
+        data::redact_urls_in(&format!("{args:?}"))
+    );
+
+    data::init_fetch_options(data::FetchOptions {
+        offline: args.offline,
+        cache_ttl: args.cache_ttl,
+        ignore_cache_control: args.ignore_cache_control,
+        connect_timeout: Duration::from_secs(args.connect_timeout),
+        read_timeout: Duration::from_secs(args.read_timeout),
+        max_retries: args.max_retries,
+        user_agent: args.user_agent.clone(),
+        max_redirects: args.max_redirects,
+        proxy: args
+            .proxy
+            .as_deref()
+            .map(|proxy| reqwest::Proxy::all(proxy).expect("proxy is checked in cli::validate")),
+    })
+    .unwrap();
+    data::init_timeline_options(data::TimelineOptions {
+        max_future_skew: Duration::from_secs(args.max_future_skew),
+        min_year: args.min_year,
+        empty_placeholder: args.empty_placeholder.clone(),
+    })
+    .unwrap();
+    data::init_backup_count(args.backups).unwrap();
+    data::init_dry_run(args.dry_run).unwrap();
+    if let Some(path) = &args.channels {
+        data::init_channels_file(path.clone()).unwrap();
+    }
+    let seed = args.seed.unwrap_or_else(rand::random);
+    debug!("Using RNG seed {seed}");
+    data::init_rng_seed(seed).unwrap();
+    html::init_template_vars(args.template_vars.clone()).unwrap();
+
+    if args.check_templates {
+        check_templates_handler(&args);
+    }
 
     use cli::{FeedSubcommand, Subcommand};
     match args.clone().command.unwrap_or_default() {
-        Subcommand::Serve { .. } => serve_handler(),
-        Subcommand::Dump { file } => dump_handler(file, &args),
+        Subcommand::Serve {
+            port,
+            bind,
+            open,
+            no_open,
+            refresh,
+            since,
+            stdin,
+        } => {
+            let open = open && !no_open && std::env::var_os("NOOS_NO_OPEN").is_none();
+            let bind: std::net::IpAddr = bind
+                .parse()
+                .expect("bind address is checked in cli::validate");
+            serve_handler(&args, (bind, port).into(), open, refresh, since, stdin);
+        }
+        Subcommand::Dump(dump_args) => dump_handler(&dump_args, &args),
         Subcommand::Feed(cmd) => match cmd {
-            FeedSubcommand::Import { file } => import_handler(&file),
-            FeedSubcommand::Export { file } => export_handler(&file),
-            FeedSubcommand::List => list_handler(),
-            FeedSubcommand::Add { feed } => add_handler(feed),
+            FeedSubcommand::Import { file, interactive } => import_handler(&file, interactive),
+            FeedSubcommand::Export { file, format } => export_handler(&file, format),
+            FeedSubcommand::List { verbose } => list_handler(verbose, args.quiet),
+            FeedSubcommand::Search { term } => search_handler(&term, args.quiet),
+            FeedSubcommand::Discover { url, add } => discover_handler(&url, add, args.quiet),
+            FeedSubcommand::Add { feed, verify } => add_handler(feed, verify),
             FeedSubcommand::Remove { feed } => remove_handler(feed),
+            FeedSubcommand::Test { feed } => test_handler(feed),
+            FeedSubcommand::Rename { feed, name } => rename_handler(feed, name),
+            FeedSubcommand::Tag { feed, tags } => tag_handler(feed, &tags),
+            FeedSubcommand::Untag { feed, tags } => untag_handler(feed, &tags),
+            FeedSubcommand::Dedupe => dedupe_handler(),
         },
+        Subcommand::Config(cli::ConfigSubcommand::Path) => config_path_handler(&args),
+        Subcommand::Undo => undo_handler(),
+        Subcommand::Completions { .. } => unreachable!("handled in cli::parse"),
+        Subcommand::Manpage { .. } => unreachable!("handled in cli::parse"),
     }
 
     info!("Success! Exiting...");
+    logger::flush();
 }
 
-/// Dump aggregated feed items to static HTML file
-fn dump_handler<P: AsRef<Path>>(file: P, args: &cli::Args) {
-    let urls = data::read_urls_from_config_channels_file();
-    info!("Found {} channel URLs in channels file.", urls.len());
-    for url in &urls {
-        info!("Loading channel from URL: {}", url);
-        let channel = get_feed(&url);
-        if let Some(ch) = channel {
-            data::add_channel_items(&ch);
+/// Dump aggregated feed items to static HTML file(s)
+/// With a non-zero `page_size`, the timeline is split across multiple files
+/// Non-html formats bypass the templates and are never paginated
+fn dump_handler(dump_args: &cli::DumpArgs, args: &cli::Args) {
+    let cli::DumpArgs {
+        file,
+        tags,
+        page_size,
+        format,
+        feed_title,
+        cache_file,
+        since,
+        query,
+        regex,
+        fail_on_empty,
+        open,
+        no_open,
+        stdin,
+    } = dump_args;
+    let (page_size, format) = (*page_size, *format);
+    let open = *open && !*no_open && std::env::var_os("NOOS_NO_OPEN").is_none();
+
+    let fetch_report = match cache_file.as_deref() {
+        Some(path) if path.exists() => {
+            info!("Loading timeline from cache file '{}'", path.display());
+            match serialize::load_cache(path) {
+                Ok(items) => data::add_timeline_items(items),
+                Err(e) => {
+                    error!("Fatal: Failed to load timeline from cache file: {e}");
+                    logger::exit(1);
+                }
+            }
+            None
+        }
+        cache_file => {
+            let entries = read_feed_entries(*stdin);
+            let fetch_report = fetch_timeline(args, &entries, *stdin);
+            exit_if_all_failed(&fetch_report);
+            if let Some(path) = cache_file {
+                info!("Saving timeline to cache file '{}'", path.display());
+                let timeline = &data::data_store().timeline;
+                if let Err(e) = serialize::save_cache_compressed(path, timeline, 3) {
+                    let message = format!("Failed to save timeline to cache file: {e}");
+                    exit_with_report(Some(&fetch_report), &message);
+                }
+            }
+            Some(fetch_report)
         }
+    };
+
+    if !tags.is_empty() {
+        let count = data::filter_timeline_by_tags(tags);
+        info!("{count} items remaining after filtering by tags {tags:?}");
     }
 
-    let (page_template, item_template) =
-        html::load_templates_or_default(args.page_template.clone(), args.item_template.clone());
+    if let Some(since) = since {
+        let count = data::filter_timeline_since(*since);
+        let seconds = since.as_secs();
+        info!("{count} items remaining after filtering items older than {seconds} seconds");
+    }
+
+    if let Some(query) = query.as_deref().filter(|query| !query.is_empty()) {
+        match data::filter_timeline_by_query(query, *regex) {
+            Ok(count) => info!("{count} items remaining after filtering by query '{query}'"),
+            Err(e) => {
+                let message = format!("Invalid --query regex: {e}");
+                exit_with_report(fetch_report.as_ref(), &message);
+            }
+        }
+    }
+
+    if args.strip_tracking_params {
+        data::strip_timeline_tracking_params(&args.tracking_params);
+    }
 
-    let html = page_template.render((&data::data_store().timeline, &item_template));
+    let timeline = &data::data_store().timeline;
 
-    html::dump_html_to_file(&html, file);
+    if *fail_on_empty && timeline.is_empty() {
+        let message = "No items to dump (see --fail-on-empty)";
+        exit_with_report(fetch_report.as_ref(), message);
+    }
+
+    if format != cli::OutputFormat::Html {
+        if page_size != 0 {
+            warn!("--page-size only applies to html output, writing a single file");
+        }
+        let output = match format {
+            cli::OutputFormat::Json => output::render_json(timeline),
+            cli::OutputFormat::Rss => output::render_rss(timeline, feed_title),
+            cli::OutputFormat::Jsonfeed => output::render_jsonfeed(timeline, feed_title),
+            cli::OutputFormat::Html => unreachable!(),
+        };
+        output::dump_to_file(&output, file);
+        if let Some(report) = &fetch_report {
+            report.log();
+        }
+        if open {
+            open_in_browser(&file.to_string_lossy());
+        }
+        return;
+    }
+
+    let (page_template, item_template, group_template) = load_configured_templates(args);
+
+    let pages = match page_size {
+        0 => vec![timeline.as_slice()],
+        _ if timeline.is_empty() => vec![timeline.as_slice()],
+        n => timeline.chunks(n).collect(),
+    };
+    let page_count = pages.len();
+    let page_name = |n: usize| {
+        let path = html::page_file_path(file.as_ref(), n);
+        path.file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    };
+
+    for (i, items) in pages.into_iter().enumerate() {
+        let n = i + 1;
+        let (prev_page, next_page) = (
+            (page_count > 1 && n > 1).then(|| page_name(n - 1)),
+            (n < page_count).then(|| page_name(n + 1)),
+        );
+        let html = page_template.render(&html::PageContext {
+            items,
+            item_template: &item_template,
+            group_template: &group_template,
+            prev_page: prev_page.as_deref(),
+            next_page: next_page.as_deref(),
+        });
+
+        html::dump_html_to_file(&html, html::page_file_path(file.as_ref(), n));
+    }
+
+    if let Some(report) = &fetch_report {
+        report.log();
+    }
+    if open {
+        open_in_browser(&file.to_string_lossy());
+    }
+}
+
+/// Log the fetch report (if any) and a fatal error, then exit with status 1
+/// So the fetch summary is logged even when a run fails after fetching
+fn exit_with_report(report: Option<&data::FetchReport>, message: &str) -> ! {
+    if let Some(report) = report {
+        report.log();
+    }
+    error!("Fatal: {message}");
+    logger::exit(1);
+}
+
+/// Exit with the fetch report if there were feeds to fetch, but all of them failed
+fn exit_if_all_failed(report: &data::FetchReport) {
+    if report.succeeded() == 0 && !report.feeds.is_empty() {
+        let message = format!("Failed to load any of {} channels", report.feeds.len());
+        exit_with_report(Some(report), &message);
+    }
+}
+
+/// Load the page, item and group templates, with the item template configured by `args`
+fn load_configured_templates(
+    args: &cli::Args,
+) -> (html::PageTemplate, html::ItemTemplate, html::GroupTemplate) {
+    let (page_template, item_template, group_template) = html::load_templates_or_default(
+        args.page_template.clone(),
+        args.item_template.clone(),
+        args.group_template.clone(),
+    );
+    let item_template = item_template
+        .sanitize_html(args.sanitize_html)
+        .datetime_formats(&args.date_format, &args.time_format);
+    (page_template, item_template, group_template)
+}
+
+/// Open a file or URL in the default browser (via `xdg-open`, `open` or `start`)
+/// Failing to do so only logs a warning
+fn open_in_browser(target: &str) {
+    use std::process::{Command, Stdio};
+
+    #[cfg(target_os = "windows")]
+    let mut command = Command::new("cmd");
+    #[cfg(target_os = "windows")]
+    command.args(["/C", "start", ""]);
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = Command::new("xdg-open");
+
+    info!("Opening '{target}' in the default browser...");
+    let status = command
+        .arg(target)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => warn!("Failed to open '{target}' in the default browser ({status})"),
+        Err(e) => warn!("Failed to open '{target}' in the default browser: {e}"),
+    }
+}
+
+/// Read the feeds to fetch from the channels file (or stdin)
+fn read_feed_entries(from_stdin: bool) -> Vec<data::FeedEntry> {
+    if from_stdin {
+        let entries = data::read_urls_from_stdin();
+        info!("Found {} channel URLs on stdin.", entries.len());
+        entries
+    } else {
+        let entries = data::read_urls_from_config_channels_file();
+        info!("Found {} channel URLs in channels file.", entries.len());
+        entries
+    }
+}
+
+/// Fetch all `entries` into the data store timeline
+/// Feed URLs that moved or advertise a canonical URL are updated as configured,
+/// unless the feeds were read from stdin
+/// Returns: the report of the fetch, which is also kept in the data store
+fn fetch_timeline(
+    args: &cli::Args,
+    entries: &[data::FeedEntry],
+    from_stdin: bool,
+) -> data::FetchReport {
+    let results = data::aggregate_detailed(entries);
+    let report = data::FetchReport::new(&results);
+    data::data_store().fetch_report = Some(report.clone());
+    info!(
+        "Loaded {} of {} channels.",
+        report.succeeded(),
+        results.len()
+    );
+
+    let mut canonical_urls = Vec::new();
+    let mut moved_urls = Vec::new();
+    for result in results {
+        let url = &data::redact_url(&result.url);
+        if let Some(title) = &result.title {
+            debug!("Adding {} items from '{title}' ({url})", result.items.len());
+        }
+        let (canonical_url, moved_to) = data::suggest_url_updates(&result);
+        if let Some(canonical_url) = canonical_url {
+            canonical_urls.push((result.url.clone(), canonical_url));
+        }
+        if let Some(moved_to) = moved_to {
+            moved_urls.push((result.url.clone(), moved_to));
+        }
+        data::add_timeline_items(result.items);
+    }
+
+    if from_stdin {
+        return report;
+    }
+
+    if args.update_moved && !moved_urls.is_empty() {
+        data::replace_urls_in_config_channels_file(&moved_urls);
+    }
+
+    if args.update_canonical && !canonical_urls.is_empty() {
+        data::replace_urls_in_config_channels_file(&canonical_urls);
+    }
+
+    report
+}
+
+/// Validate the page and item templates without fetching any feeds, then exit
+/// Exits with status 1 if any template has problems
+fn check_templates_handler(args: &cli::Args) -> ! {
+    let (page_template, item_template, group_template) = html::load_templates_or_default(
+        args.page_template.clone(),
+        args.item_template.clone(),
+        args.group_template.clone(),
+    );
+
+    if !html::check_templates(&page_template, &item_template, &group_template) {
+        error!("Template check failed, see warnings above.");
+        logger::exit(1);
+    }
+    info!("Template check passed.");
+    logger::exit(0);
 }
 
 /// Start web server to serve aggregated feed items
-/// Currently unimplemented -- just errs and exits
-fn serve_handler() {
-    // TODO: implement web server
-    error!("Fatal: The 'serve' subcommand is unimplemented. Use 'dump' for now.");
-    std::process::exit(1);
+/// All feeds are refetched and the page re-rendered every `refresh` (unless zero)
+/// Feeds read from stdin are read once and refetched on each refresh
+fn serve_handler(
+    args: &cli::Args,
+    addr: std::net::SocketAddr,
+    open: bool,
+    refresh: Duration,
+    since: Option<Duration>,
+    stdin: bool,
+) {
+    let listener = match std::net::TcpListener::bind(addr) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Fatal: Failed to bind to {addr}: {e}");
+            logger::exit(1);
+        }
+    };
+    let url = server::local_url(listener.local_addr().unwrap_or(addr));
+
+    let templates = load_configured_templates(args);
+    let stdin_entries = stdin.then(|| read_feed_entries(true));
+    let render = || {
+        let entries = match &stdin_entries {
+            Some(entries) => entries.clone(),
+            None => read_feed_entries(false),
+        };
+        render_served_page(args, &entries, stdin, since, &templates)
+    };
+
+    let (report, page) = render();
+    exit_if_all_failed(&report);
+    report.log();
+    let page = std::sync::Arc::new(std::sync::RwLock::new(page));
+
+    let server_page = std::sync::Arc::clone(&page);
+    let server = std::thread::spawn(move || server::serve(listener, server_page));
+    info!("Serving on {url}");
+    if open {
+        open_in_browser(&url);
+    }
+
+    if refresh.is_zero() {
+        let _ = server.join();
+        return;
+    }
+    loop {
+        std::thread::sleep(refresh);
+        info!("Refreshing all feeds...");
+        let (report, new_page) = render();
+        report.log();
+        if report.succeeded() == 0 && !report.feeds.is_empty() {
+            warn!("Failed to load any channels, still serving the previous page");
+            continue;
+        }
+        *page.write().unwrap() = new_page;
+    }
+}
+
+/// Fetch `entries` into a fresh timeline and render it as a single page for `serve_handler`
+/// Returns: the report of the fetch and the page to serve
+fn render_served_page(
+    args: &cli::Args,
+    entries: &[data::FeedEntry],
+    from_stdin: bool,
+    since: Option<Duration>,
+    (page_template, item_template, group_template): &(
+        html::PageTemplate,
+        html::ItemTemplate,
+        html::GroupTemplate,
+    ),
+) -> (data::FetchReport, server::Page) {
+    data::clear_timeline();
+    let report = fetch_timeline(args, entries, from_stdin);
+
+    if let Some(since) = since {
+        let count = data::filter_timeline_since(since);
+        let seconds = since.as_secs();
+        info!("{count} items remaining after filtering items older than {seconds} seconds");
+    }
+    if args.strip_tracking_params {
+        data::strip_timeline_tracking_params(&args.tracking_params);
+    }
+
+    let timeline = &data::data_store().timeline;
+    let html = page_template.render(&html::PageContext {
+        items: timeline,
+        item_template,
+        group_template,
+        prev_page: None,
+        next_page: None,
+    });
+    let feeds_json = output::render_fetch_report_json(&report);
+    (report, server::Page::new(html, feeds_json))
+}
+
+/// Restore the channels file from its most recent backup
+fn undo_handler() {
+    match data::restore_latest_channels_backup() {
+        Some(backup) if data::dry_run() => info!(
+            "Would restore channels file from '{}' (dry run)",
+            backup.display()
+        ),
+        Some(backup) => info!("Restored channels file from '{}'", backup.display()),
+        None => {
+            warn!("No channels file backup found. Nothing to undo...");
+            logger::exit(0);
+        }
+    }
 }
 
 /// Import OPML, merge with existing channels, and export to channels file
-fn import_handler(file: &str) {
-    // Get urls to import from OPML file
-    let mut urls = data::import_opml_channel_urls(file);
+/// With `interactive`, the feeds to import are picked from a checklist
+fn import_handler(file: &str, interactive: bool) {
+    // Read existing entries from channels file first, so their names and tags take precedence
+    let mut entries = data::read_urls_from_config_channels_file();
+
+    // Add urls to import from OPML, JSON or text file
+    let mut imported = data::import_feed_entries(file);
+    if interactive {
+        imported = pick_feeds(imported);
+    }
+    entries.extend(imported.into_iter().map(|(entry, _)| entry));
+
+    let (entries, skipped) = data::dedupe_feed_entries(entries);
+    if skipped > 0 {
+        info!("Skipped {skipped} duplicate feed URLs");
+    }
+
+    // Write all entries to channels file
+    data::export_channel_urls_to_config(&entries);
+}
+
+/// Let the user pick feeds (with their titles) from a terminal checklist
+/// Without a terminal, all feeds are picked. Exits if the checklist is cancelled.
+fn pick_feeds(entries: Vec<(data::FeedEntry, String)>) -> Vec<(data::FeedEntry, String)> {
+    use std::io::IsTerminal;
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        warn!("Not running in a terminal, ignoring --interactive and importing all feeds");
+        return entries;
+    }
+
+    let labels = entries
+        .iter()
+        .map(|(entry, title)| match title.is_empty() {
+            true => entry.url.clone(),
+            false => format!("{title} ({})", entry.url),
+        })
+        .collect::<Vec<_>>();
 
-    // Also read existing urls from channels file
-    urls.extend(data::read_urls_from_config_channels_file());
+    let selection = dialoguer::MultiSelect::new()
+        .with_prompt("Select feeds to import (space to toggle, enter to confirm)")
+        .items(&labels)
+        .interact_opt();
 
-    // Write all urls to channels file
-    data::export_channel_urls_to_config(&urls);
+    let selected = match selection {
+        Ok(Some(selected)) => selected,
+        Ok(None) => {
+            info!("Import cancelled, no feeds were imported.");
+            logger::exit(0);
+        }
+        Err(e) => {
+            error!("Fatal: Failed to show feed checklist: {e}");
+            logger::exit(1);
+        }
+    };
+
+    info!("Selected {} of {} feeds", selected.len(), entries.len());
+    entries
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| selected.contains(i))
+        .map(|(_, entry)| entry)
+        .collect()
 }
 
 /// Export channels from channels file to OPML
-fn export_handler(file: &str) {
-    info!("Exporting feeds to OPML file: '{file}'");
+fn export_handler(file: &str, format: cli::ExportFormat) {
+    info!("Exporting feeds to {format:?} file: '{file}'");
     if std::path::PathBuf::from(&file).exists() {
-        error!("Fatal: OPML file '{file}' already exists.",);
-        std::process::exit(1);
+        error!("Fatal: Export file '{file}' already exists.",);
+        logger::exit(1);
     }
 
-    let urls = data::read_urls_from_config_channels_file();
-    let channels = data::open_rss_channels(&urls);
+    let entries = data::read_urls_from_config_channels_file();
+    let urls = entries
+        .iter()
+        .map(|entry| entry.url.clone())
+        .collect::<Vec<_>>();
 
-    data::export_opml(file, channels);
+    if data::dry_run() {
+        info!("Dry run: not writing {} URLs to '{file}'", urls.len());
+        urls.iter()
+            .for_each(|url| info!("+ {}", data::redact_url(url)));
+        return;
+    }
+
+    let result = match format {
+        cli::ExportFormat::Opml => {
+            let channels = data::open_rss_channels(&urls);
+            data::export_opml(file, entries.into_iter().zip(channels).collect());
+            Ok(())
+        }
+        cli::ExportFormat::Txt => data::export_channel_urls(file, &urls),
+        cli::ExportFormat::Json => data::export_channel_entries_json(file, &entries),
+    };
+    if let Err(e) = result {
+        error!("Fatal: Failed to export feeds: {e}");
+        logger::exit(1);
+    }
 
     info!(
-        "Exported {} URLs from channels file to OPML file",
+        "Exported {} URLs from channels file to {format:?} file",
         urls.len()
     );
 }
 
 /// List all feed URLs in channels file
-fn list_handler() {
-    data::read_urls_from_config_channels_file()
+/// With `verbose`, all feeds are fetched to show their title and item count
+/// With `quiet`, nothing is printed
+fn list_handler(verbose: bool, quiet: bool) {
+    let entries = data::read_urls_from_config_channels_file();
+    if quiet {
+        return;
+    }
+    if !verbose {
+        entries.iter().for_each(|entry| println!("{}", entry.url));
+        return;
+    }
+
+    for result in data::aggregate_detailed(&entries) {
+        match (&result.outcome, &result.title) {
+            (Ok(()), Some(title)) => {
+                println!("{}\t{title}\t{} items", result.url, result.items.len())
+            }
+            _ => println!("{}\t[unreachable]", result.url),
+        }
+    }
+}
+
+/// Print the resolved paths of all files in the config directory, and whether they exist
+/// Paths overridden on the command line (or in the config file) are shown instead
+/// Does not create the channels file if it is missing
+fn config_path_handler(args: &cli::Args) {
+    let (Some(config_base), Some(cache_dir)) = (data::config_base(), data::feed_cache_dir()) else {
+        error!("Fatal: Failed to get config directory");
+        logger::exit(1);
+    };
+    if args.quiet {
+        return;
+    }
+
+    let template = |cli_arg: &Option<std::path::PathBuf>, name: &str| {
+        cli_arg.clone().unwrap_or_else(|| config_base.join(name))
+    };
+    let paths = [
+        ("config dir", config_base.clone()),
+        ("config file", config_base.join("config.toml")),
+        ("channels file", data::channels_file_path()),
+        (
+            "page template",
+            template(&args.page_template, html::PAGE_TEMPLATE_FILE),
+        ),
+        (
+            "item template",
+            template(&args.item_template, html::ITEM_TEMPLATE_FILE),
+        ),
+        (
+            "group template",
+            template(&args.group_template, html::GROUP_TEMPLATE_FILE),
+        ),
+        ("stylesheet", config_base.join(html::STYLESHEET_FILE)),
+        ("cache dir", cache_dir),
+    ];
+
+    for (name, path) in paths {
+        let status = match path.exists() {
+            true => "exists",
+            false => "missing",
+        };
+        println!("{name}\t{}\t{status}", path.display());
+    }
+}
+
+/// List the feed URLs in channels file whose URL, name or cached title contains a term
+/// Prints like `list_handler`, without fetching any feeds
+fn search_handler(term: &str, quiet: bool) {
+    let entries = data::read_urls_from_config_channels_file();
+    let term = term.to_lowercase();
+    let matches = |text: &str| text.to_lowercase().contains(&term);
+
+    let found = entries
         .iter()
-        .for_each(|url| println!("{url}"));
+        .filter(|entry| {
+            matches(&entry.url)
+                || entry.name.as_deref().is_some_and(matches)
+                || data::cached_channel_title(&entry.url).is_some_and(|title| matches(&title))
+        })
+        .collect::<Vec<_>>();
+
+    info!(
+        "Found {} of {} feeds matching '{term}'",
+        found.len(),
+        entries.len()
+    );
+    if !quiet {
+        found.iter().for_each(|entry| println!("{}", entry.url));
+    }
+}
+
+/// Print the feeds a website advertises, as `<url>\t<title>` lines
+/// With `add`, the first discovered feed is added to the channels file
+fn discover_handler(url: &str, add: bool, quiet: bool) {
+    info!("Discovering feeds of '{}'", data::redact_url(url));
+
+    let feeds = match data::discover_feeds(url) {
+        Ok(feeds) => feeds,
+        Err(e) => {
+            error!("Fatal: Failed to fetch '{}': {e}", data::redact_url(url));
+            logger::exit(1);
+        }
+    };
+
+    if feeds.is_empty() {
+        error!("Fatal: No feeds found on '{}'", data::redact_url(url));
+        logger::exit(1);
+    }
+    info!("Found {} feeds", feeds.len());
+    if !quiet {
+        for feed in &feeds {
+            println!(
+                "{}\t{}",
+                feed.url,
+                feed.title.as_deref().unwrap_or_default()
+            );
+        }
+    }
+
+    if add {
+        if feeds.len() > 1 {
+            info!("Adding the first of {} discovered feeds", feeds.len());
+        }
+        add_handler(feeds[0].url.clone(), false);
+    }
 }
 
 /// Add a feed URL to channels file
-fn add_handler(feed: String) {
-    info!("Adding feed URL: '{feed}'");
+/// With `verify`, the feed is fetched and parsed first
+fn add_handler(feed: String, verify: bool) {
+    info!("Adding feed URL: '{}'", data::redact_url(&feed));
+
+    if let Err(e) = data::validate_feed_url(&feed) {
+        error!("Fatal: Invalid feed URL '{}': {e}", data::redact_url(&feed));
+        logger::exit(1);
+    }
+
+    if verify {
+        match data::open_rss_channel(&feed) {
+            Ok((channel, _)) => info!("Verified feed '{}'", channel.title()),
+            Err(e) => {
+                error!("Fatal: Failed to verify feed: {e}");
+                logger::exit(1);
+            }
+        }
+    }
 
-    let mut urls = data::read_urls_from_config_channels_file();
-    if urls.contains(&feed) {
-        warn!("Feed URL '{feed}' is already in channels file. Skipping...");
-        std::process::exit(0);
+    let entries = data::read_urls_from_config_channels_file();
+    if entries.iter().any(|entry| entry.url == feed) {
+        warn!(
+            "Feed URL '{}' is already in channels file. Skipping...",
+            data::redact_url(&feed)
+        );
+        logger::exit(0);
     }
 
-    urls.push(feed);
-    data::export_channel_urls_to_config(&urls);
+    // Append to the raw lines to keep comments intact
+    let mut lines = data::read_lines_from_config_channels_file();
+    lines.push(feed);
+    data::export_channel_urls_to_config(&lines);
 }
 
 /// Remove a feed URL from channels file
 fn remove_handler(feed: String) {
-    info!("Removing feed URL: '{feed}'");
+    info!("Removing feed URL: '{}'", data::redact_url(&feed));
 
-    let mut urls = data::read_urls_from_config_channels_file();
-    if !urls.contains(&feed) {
-        warn!("Feed URL '{feed}' not found in channels file. Skipping...");
-        std::process::exit(0);
+    let entries = data::read_urls_from_config_channels_file();
+    if !entries.iter().any(|entry| entry.url == feed) {
+        warn!(
+            "Feed URL '{}' not found in channels file. Skipping...",
+            data::redact_url(&feed)
+        );
+        logger::exit(0);
     }
 
-    urls.retain(|url| url != &feed);
-    data::export_channel_urls_to_config(&urls);
+    // Filter the raw lines to keep comments intact
+    let mut lines = data::read_lines_from_config_channels_file();
+    lines.retain(|line| {
+        data::is_comment_line(line) || line.parse::<data::FeedEntry>().is_ok_and(|e| e.url != feed)
+    });
+    data::export_channel_urls_to_config(&lines);
 }
 
-/// Fetch and parse an RSS feed from a URL
-fn get_feed(url: &str) -> Option<rss::Channel> {
-    // Get a sample rss feed
-    match data::open_rss_channel(url) {
+/// Fetch a single feed and print a diagnosis to stdout
+/// Exits with status 1 if the feed couldn't be fetched or parsed
+fn test_handler(feed: String) {
+    info!("Testing feed URL: '{}'", data::redact_url(&feed));
+
+    let diagnosis = match data::diagnose_feed(&feed) {
+        Ok(diagnosis) => diagnosis,
         Err(e) => {
-            error!("Failed to open RSS channel: {e}. Skipping channel...");
-            None
+            println!("error: {e}");
+            logger::exit(1);
+        }
+    };
+
+    println!("status: {}", diagnosis.status);
+    println!("final url: {}", data::redact_url(&diagnosis.final_url));
+    println!("format: {}", diagnosis.format);
+
+    let channel = match diagnosis.channel {
+        Ok(channel) => channel,
+        Err(e) => {
+            println!("error: failed to parse channel: {e}");
+            logger::exit(1);
+        }
+    };
+
+    let items = data::channel_items(&channel, &data::FeedEntry::from(feed));
+    let newest = items.iter().map(|item| item.timestamp).max();
+    println!("title: {}", channel.title());
+    println!("items: {}", items.len());
+    match newest.and_then(|ts| chrono::DateTime::from_timestamp(ts, 0)) {
+        Some(newest) => println!("newest item: {}", newest.to_rfc3339()),
+        None => println!("newest item: (none)"),
+    }
+}
+
+/// Set the display name of a feed in channels file
+fn rename_handler(feed: String, name: String) {
+    info!(
+        "Renaming feed URL '{}' to '{name}'",
+        data::redact_url(&feed)
+    );
+
+    let name = Some(name).filter(|name| !name.trim().is_empty());
+    if !data::modify_entry_in_config_channels_file(&feed, |entry| entry.name = name) {
+        warn!(
+            "Feed URL '{}' not found in channels file. Skipping...",
+            data::redact_url(&feed)
+        );
+        logger::exit(0);
+    }
+}
+
+/// Add tags to a feed in channels file
+fn tag_handler(feed: String, tags: &[String]) {
+    info!(
+        "Tagging feed URL '{}' with {tags:?}",
+        data::redact_url(&feed)
+    );
+
+    let found = data::modify_entry_in_config_channels_file(&feed, |entry| {
+        for tag in tags {
+            if entry.tags.contains(tag) {
+                debug!("Feed already has tag '{tag}'");
+            } else {
+                entry.tags.push(tag.clone());
+            }
+        }
+    });
+    if !found {
+        warn!(
+            "Feed URL '{}' not found in channels file. Skipping...",
+            data::redact_url(&feed)
+        );
+        logger::exit(0);
+    }
+}
+
+/// Remove tags from a feed in channels file
+fn untag_handler(feed: String, tags: &[String]) {
+    info!(
+        "Untagging feed URL '{}' from {tags:?}",
+        data::redact_url(&feed)
+    );
+
+    let found = data::modify_entry_in_config_channels_file(&feed, |entry| {
+        for tag in tags {
+            if !entry.tags.contains(tag) {
+                warn!("Feed doesn't have tag '{tag}'");
+            }
         }
-        Ok(c) => c.into(),
+        entry.tags.retain(|tag| !tags.contains(tag));
+    });
+    if !found {
+        warn!(
+            "Feed URL '{}' not found in channels file. Skipping...",
+            data::redact_url(&feed)
+        );
+        logger::exit(0);
     }
 }
+
+/// Remove duplicate feed URLs from channels file
+fn dedupe_handler() {
+    info!("Removing duplicate feed URLs from channels file...");
+
+    let lines = data::read_lines_from_config_channels_file();
+    let (deduped, removed) = data::dedupe_channel_lines(&lines);
+
+    if removed == 0 {
+        info!("No duplicate feed URLs found in channels file.");
+        return;
+    }
+
+    data::export_channel_urls_to_config(&deduped);
+    info!("Removed {removed} duplicate feed URLs from channels file");
+}
@@ -1,76 +1,188 @@
 #![allow(dead_code)]
 //! Serialization and deserialization of data using bincode.
 //! Used mainly for caching data during testing.
+//! Caches may be zstd-compressed, which is detected when loading.
 
 use serde::{Deserialize, Serialize};
-use std::io::{BufReader, BufWriter};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
 
-use crate::{error, log};
+use crate::{debug, log, warn};
 
 #[derive(Serialize, Deserialize)]
 pub struct SerdeWrapper<T>(pub T);
 
 /// Save a serializable value to a file using bincode.
 /// Used for testing without constantly refetching data.
-/// Exits the program on failure.
 ///
 /// Example:
-/// `serialize::save_cache("cache/feed.bin", &channel);`
-pub fn save_cache<T, P>(path: P, value: &T)
+/// `serialize::save_cache("cache/feed.bin", &channel)?;`
+pub fn save_cache<T, P>(path: P, value: &T) -> Result<(), String>
 where
     T: serde::Serialize,
     P: AsRef<std::path::Path>,
 {
-    let save = || -> Result<(), String> {
-        let file = std::fs::File::create(path).map_err(|e| {
-            error!("Failed to create cache file: {}", e);
-            e.to_string()
-        })?;
-        let mut writer = BufWriter::new(file);
-        bincode::serde::encode_into_std_write(value, &mut writer, bincode::config::standard())
-            .map_err(|e| {
-                error!("Failed to encode cache data: {}", e);
-                e.to_string()
-            })?;
-        Ok(())
-    };
+    let file =
+        std::fs::File::create(path).map_err(|e| format!("failed to create cache file: {e}"))?;
+    let mut writer = BufWriter::new(file);
+    encode_into(value, &mut writer)?;
+    writer
+        .flush()
+        .map_err(|e| format!("failed to write cache file: {e}"))
+}
 
-    if let Err(e) = save() {
-        error!("Failed to save cache: {}", e);
-        std::process::exit(1);
-    }
+/// Save a serializable value to a zstd-compressed file using bincode.
+/// The compression `level` ranges from 1 (fastest) to 22 (best), with 0 meaning zstd's default.
+/// Load it with `load_cache` as usual.
+///
+/// Example:
+/// `serialize::save_cache_compressed("cache/timeline.bin", &timeline, 3)?;`
+pub fn save_cache_compressed<T, P>(path: P, value: &T, level: i32) -> Result<(), String>
+where
+    T: serde::Serialize,
+    P: AsRef<std::path::Path>,
+{
+    let file =
+        std::fs::File::create(path).map_err(|e| format!("failed to create cache file: {e}"))?;
+    let mut writer = zstd::stream::Encoder::new(BufWriter::new(file), level)
+        .map_err(|e| format!("failed to create cache encoder: {e}"))?;
+    encode_into(value, &mut writer)?;
+    writer
+        .finish()
+        .and_then(|mut writer| writer.flush())
+        .map_err(|e| format!("failed to write cache file: {e}"))
+}
+
+/// Encode a value into a writer using bincode
+fn encode_into<T, W>(value: &T, writer: &mut W) -> Result<(), String>
+where
+    T: serde::Serialize,
+    W: Write,
+{
+    bincode::serde::encode_into_std_write(value, writer, bincode::config::standard())
+        .map_err(|e| format!("failed to encode cache data: {e}"))?;
+    Ok(())
 }
 
+/// The magic bytes at the start of a zstd frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
 /// Load a deserializable value from a file using bincode.
 /// Used for testing without constantly refetching data.
+/// Zstd-compressed files (see `save_cache_compressed`) are decompressed.
 ///
 /// Example:
-/// `let channel: rss::Channel = serialize::load_cache("cache/feed.bin");`
-pub fn load_cache<T, P>(path: P) -> T
+/// `let channel: rss::Channel = serialize::load_cache("cache/feed.bin")?;`
+pub fn load_cache<T, P>(path: P) -> Result<T, String>
 where
     T: for<'de> serde::de::DeserializeOwned,
     P: AsRef<std::path::Path>,
 {
-    let load = || -> Result<SerdeWrapper<T>, String> {
-        let file = std::fs::File::open(path).map_err(|e| {
-            error!("Failed to open cache file: {}", e);
-            e.to_string()
-        })?;
-        let mut reader = BufReader::new(file);
-        let decoded: SerdeWrapper<T> =
-            bincode::serde::decode_from_std_read(&mut reader, bincode::config::standard())
-                .map_err(|e| {
-                    error!("Failed to decode cache data: {}", e);
-                    e.to_string()
-                })?;
-        Ok(decoded)
+    let file = std::fs::File::open(path).map_err(|e| format!("failed to open cache file: {e}"))?;
+    let mut reader = BufReader::new(file);
+    let header = reader
+        .fill_buf()
+        .map_err(|e| format!("failed to read cache file: {e}"))?;
+
+    let mut reader: Box<dyn Read> = match header.starts_with(&ZSTD_MAGIC) {
+        true => Box::new(
+            zstd::stream::Decoder::with_buffer(reader)
+                .map_err(|e| format!("failed to create cache decoder: {e}"))?,
+        ),
+        false => Box::new(reader),
     };
+    let decoded: SerdeWrapper<T> =
+        bincode::serde::decode_from_std_read(&mut reader, bincode::config::standard())
+            .map_err(|e| format!("failed to decode cache data: {e}"))?;
+    Ok(decoded.0)
+}
+
+/// Load a deserializable value from a file using bincode,
+/// if the file was modified less than `max_age` ago.
+/// Returns `None` if the file is missing, expired or fails to decode, so the caller can refetch.
+///
+/// Example:
+/// `let items: Option<Vec<TimelineItem>> = serialize::load_cache_if_fresh("timeline.bin", max_age);`
+pub fn load_cache_if_fresh<T, P>(path: P, max_age: std::time::Duration) -> Option<T>
+where
+    T: for<'de> serde::de::DeserializeOwned,
+    P: AsRef<std::path::Path>,
+{
+    let path = path.as_ref();
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    // Modification times in the future count as fresh
+    if modified.elapsed().is_ok_and(|age| age > max_age) {
+        debug!("Cache file '{}' expired", path.display());
+        return None;
+    }
 
-    match load() {
-        Ok(t) => t.0,
+    match load_cache(path) {
+        Ok(value) => Some(value),
         Err(e) => {
-            error!("Failed to load cache: {}", e);
-            std::process::exit(1);
+            warn!("Ignoring cache file '{}': {e}", path.display());
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A path in the temp dir, unique to this test process and `name`
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("noos-serialize-{}-{name}", std::process::id()))
+    }
+
+    fn sample() -> Vec<(String, u64)> {
+        (0..500).map(|i| (format!("item number {i}"), i)).collect()
+    }
+
+    #[test]
+    fn compressed_cache_round_trips_at_several_levels() {
+        let value = sample();
+        for level in [0, 1, 3, 19] {
+            let path = temp_path(&format!("level-{level}.bin"));
+            save_cache_compressed(&path, &value, level).unwrap();
+
+            let header = std::fs::read(&path).unwrap();
+            assert!(header.starts_with(&ZSTD_MAGIC), "level {level}");
+            let loaded: Vec<(String, u64)> = load_cache(&path).unwrap();
+            assert_eq!(loaded, value, "level {level}");
+            std::fs::remove_file(&path).unwrap();
         }
     }
+
+    #[test]
+    fn compressed_cache_is_smaller() {
+        let (plain, compressed) = (temp_path("plain.bin"), temp_path("compressed.bin"));
+        save_cache(&plain, &sample()).unwrap();
+        save_cache_compressed(&compressed, &sample(), 3).unwrap();
+
+        let size = |path: &PathBuf| std::fs::metadata(path).unwrap().len();
+        assert!(size(&compressed) < size(&plain));
+        std::fs::remove_file(&plain).unwrap();
+        std::fs::remove_file(&compressed).unwrap();
+    }
+
+    #[test]
+    fn uncompressed_cache_still_loads() {
+        let path = temp_path("uncompressed.bin");
+        save_cache(&path, &sample()).unwrap();
+
+        let header = std::fs::read(&path).unwrap();
+        assert!(!header.starts_with(&ZSTD_MAGIC));
+        let loaded: Vec<(String, u64)> = load_cache(&path).unwrap();
+        assert_eq!(loaded, sample());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn corrupt_cache_fails_to_load() {
+        let path = temp_path("corrupt.bin");
+        std::fs::write(&path, [ZSTD_MAGIC.as_slice(), b"garbage"].concat()).unwrap();
+
+        assert!(load_cache::<Vec<(String, u64)>, _>(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -0,0 +1,496 @@
+//! A minimal http server for the rendered timeline, see `serve`.
+//!
+//! Every connection is handled on its own thread and closed after a single response.
+//! The served `Page` is shared, so the caller can replace it on each refresh cycle.
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use crate::data;
+
+#[allow(unused_imports)]
+use crate::{debug, error, info, log, warn};
+
+/// The maximum size of a request line plus headers, larger requests are rejected
+const MAX_REQUEST_HEAD_SIZE: u64 = 16 * 1024;
+
+/// How long to wait for a client to send its request
+const REQUEST_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Everything served for one refresh cycle, see `Page::new`
+#[derive(Debug, Clone, Default)]
+pub struct Page {
+    html: String,
+    /// The entity tag of `html`, see `Page::new`
+    etag: String,
+    /// The gzip-compressed `html`, compressed once per refresh cycle
+    html_gzip: Vec<u8>,
+    /// The entity tag of `html_gzip`, which differs from `etag` as a different representation
+    gzip_etag: String,
+    /// The fetch report of the refresh cycle, see `output::render_fetch_report_json`
+    feeds_json: String,
+}
+
+impl Page {
+    /// Prepare the rendered html of the timeline and the JSON fetch report for serving
+    /// The html is tagged with its hash, so unchanged pages aren't resent to clients
+    pub fn new(html: String, feeds_json: String) -> Self {
+        let hash = data::stable_hash(&html);
+        Self {
+            html_gzip: gzip(html.as_bytes()),
+            html,
+            etag: format!("\"{hash:016x}\""),
+            gzip_etag: format!("\"{hash:016x}-gzip\""),
+            feeds_json,
+        }
+    }
+}
+
+/// Compress data with gzip (at the default level)
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder
+        .write_all(data)
+        .expect("writing to a vec never fails");
+    encoder.finish().expect("writing to a vec never fails")
+}
+
+/// The currently served page, replaced on each refresh cycle
+pub type SharedPage = Arc<RwLock<Page>>;
+
+/// Get the URL to open the server bound to `addr` in a browser
+/// Unspecified addresses (like `0.0.0.0`) are replaced with the loopback address
+pub fn local_url(addr: SocketAddr) -> String {
+    let ip = match addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ip => ip,
+    };
+    format!("http://{}/", SocketAddr::new(ip, addr.port()))
+}
+
+/// Accept connections on `listener` forever, serving `page` from a thread per connection
+/// NOTE: This blocks, so spawn a thread for it to keep refreshing the page
+pub fn serve(listener: TcpListener, page: SharedPage) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let page = Arc::clone(&page);
+                std::thread::spawn(move || handle_connection(stream, &page));
+            }
+            Err(e) => warn!("Failed to accept connection: {e}"),
+        }
+    }
+}
+
+/// Read a single request from `stream` and write the response
+fn handle_connection(mut stream: TcpStream, page: &RwLock<Page>) {
+    let peer = stream
+        .peer_addr()
+        .map(|addr| addr.to_string())
+        .unwrap_or_else(|_| "unknown peer".into());
+    if let Err(e) = stream.set_read_timeout(Some(REQUEST_READ_TIMEOUT)) {
+        warn!("Failed to set read timeout for connection from {peer}: {e}");
+    }
+
+    let reader = BufReader::new((&stream).take(MAX_REQUEST_HEAD_SIZE));
+    let response = match parse_request(reader) {
+        Ok(request) => {
+            let response = respond(&request, &page.read().unwrap());
+            debug!(
+                "{} {} from {peer}: {}",
+                request.method, request.path, response.status
+            );
+            response.head_only(request.method == "HEAD")
+        }
+        Err(e) => {
+            debug!("Bad request from {peer}: {e}");
+            Response::text(400, "Bad Request")
+        }
+    };
+
+    if let Err(e) = response.write_to(&mut stream) {
+        debug!("Failed to write response to {peer}: {e}");
+    }
+}
+
+/// The parts of an http request the server cares about
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Request {
+    method: String,
+    /// The request target without its query string
+    path: String,
+    /// Header names are lowercase
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Get the value of the first header named `name` (lowercase)
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Check whether the `If-None-Match` header matches `etag` (using weak comparison)
+    fn none_match(&self, etag: &str) -> bool {
+        let Some(tags) = self.header("if-none-match") else {
+            return false;
+        };
+        tags.split(',').map(str::trim).any(|tag| {
+            tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag.trim_start_matches("W/")
+        })
+    }
+
+    /// Check whether the `Accept-Encoding` header allows gzip (with a non-zero quality)
+    fn accepts_gzip(&self) -> bool {
+        let Some(encodings) = self.header("accept-encoding") else {
+            return false;
+        };
+        let quality = |name: &str| {
+            encodings.split(',').find_map(|encoding| {
+                let mut params = encoding.split(';').map(str::trim);
+                params.next()?.eq_ignore_ascii_case(name).then(|| {
+                    params
+                        .find_map(|param| param.strip_prefix("q="))
+                        .and_then(|q| q.parse::<f32>().ok())
+                        .unwrap_or(1.0)
+                })
+            })
+        };
+        quality("gzip")
+            .or_else(|| quality("x-gzip"))
+            .or_else(|| quality("*"))
+            .is_some_and(|q| q > 0.0)
+    }
+}
+
+/// Parse the request line and headers of an http/1.x request, ignoring any body
+fn parse_request(mut reader: impl BufRead) -> Result<Request, String> {
+    let mut read_line = || -> Result<String, String> {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) => Err("unexpected end of request".into()),
+            Ok(_) if !line.ends_with('\n') => Err("request head too large".into()),
+            Ok(_) => Ok(line.trim_end_matches(['\r', '\n']).to_string()),
+            Err(e) => Err(format!("failed to read request: {e}")),
+        }
+    };
+
+    let request_line = read_line()?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(format!("malformed request line '{request_line}'"));
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(format!("unsupported http version '{version}'"));
+    }
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line()?;
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(format!("malformed header '{line}'"));
+        };
+        headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+
+    Ok(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        headers,
+    })
+}
+
+/// An http response, see `respond`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Response {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+    /// Whether to leave out the body (but not its `Content-Length`), e.g. for `HEAD`
+    head_only: bool,
+}
+
+impl Response {
+    /// A response with a body of the given content type
+    fn new(status: u16, content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type", content_type.to_string())],
+            body: body.into(),
+            head_only: false,
+        }
+    }
+
+    /// An empty `304 Not Modified` response, for a matching `If-None-Match`
+    fn not_modified() -> Self {
+        Self {
+            status: 304,
+            headers: Vec::new(),
+            body: Vec::new(),
+            head_only: false,
+        }
+    }
+
+    /// A plain text response, e.g. for errors
+    fn text(status: u16, body: &str) -> Self {
+        Self::new(status, "text/plain; charset=utf-8", format!("{body}\n"))
+    }
+
+    /// Add a header to the response
+    fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    /// Set whether to leave out the body, see `Response::head_only`
+    fn head_only(mut self, head_only: bool) -> Self {
+        self.head_only = head_only;
+        self
+    }
+
+    /// Write the status line, headers and (unless `head_only`) body to `writer`
+    fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status, reason(self.status));
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        if self.status != 304 {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+        head.push_str("Connection: close\r\n\r\n");
+
+        writer.write_all(head.as_bytes())?;
+        if !self.head_only {
+            writer.write_all(&self.body)?;
+        }
+        writer.flush()
+    }
+}
+
+/// Get the reason phrase of the status codes the server uses
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "",
+    }
+}
+
+/// Route a request to the matching part of the served page
+fn respond(request: &Request, page: &Page) -> Response {
+    if !matches!(request.method.as_str(), "GET" | "HEAD") {
+        return Response::text(405, "Method Not Allowed").header("Allow", "GET, HEAD");
+    }
+
+    match request.path.as_str() {
+        "/" | "/index.html" => respond_html(request, page),
+        "/feeds.json" => Response::new(200, "application/json", page.feeds_json.as_str())
+            .header("Cache-Control", "no-cache"),
+        _ => Response::text(404, "Not Found"),
+    }
+}
+
+/// Respond with the page html (gzipped if the client accepts it), or `304` if it's unchanged
+fn respond_html(request: &Request, page: &Page) -> Response {
+    let gzip = request.accepts_gzip();
+    let etag = if gzip { &page.gzip_etag } else { &page.etag };
+
+    let response = match (request.none_match(etag), gzip) {
+        (true, _) => Response::not_modified(),
+        (false, true) => Response::new(200, "text/html; charset=utf-8", page.html_gzip.as_slice())
+            .header("Content-Encoding", "gzip"),
+        (false, false) => Response::new(200, "text/html; charset=utf-8", page.html.as_str()),
+    };
+    response
+        .header("ETag", etag.as_str())
+        .header("Vary", "Accept-Encoding")
+        .header("Cache-Control", "no-cache")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, path: &str, headers: &[(&str, &str)]) -> Request {
+        Request {
+            method: method.into(),
+            path: path.into(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn parses_request_line_and_headers() {
+        let raw =
+            "GET /index.html?x=1 HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: gzip\r\n\r\n";
+        let request = parse_request(raw.as_bytes()).unwrap();
+
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/index.html");
+        assert_eq!(
+            request.headers,
+            [
+                ("host".to_string(), "localhost".to_string()),
+                ("accept-encoding".to_string(), "gzip".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_requests() {
+        assert!(parse_request("GET /\r\n\r\n".as_bytes()).is_err());
+        assert!(parse_request("GET / SPDY/3\r\n\r\n".as_bytes()).is_err());
+        assert!(parse_request("GET / HTTP/1.1\r\nno colon\r\n\r\n".as_bytes()).is_err());
+        assert!(parse_request("GET / HTTP/1.1\r\nHost: x\r\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn serves_page_html_at_root() {
+        let page = Page::new("<p>hi</p>".into(), "{}".into());
+
+        let response = respond(&request("GET", "/", &[]), &page);
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"<p>hi</p>");
+        assert_eq!(
+            respond(&request("GET", "/index.html", &[]), &page),
+            response
+        );
+
+        assert_eq!(respond(&request("GET", "/nope", &[]), &page).status, 404);
+        assert_eq!(respond(&request("POST", "/", &[]), &page).status, 405);
+    }
+
+    #[test]
+    fn replies_not_modified_to_matching_etag() {
+        let page = Page::new("<p>hi</p>".into(), "{}".into());
+        let etag = page.etag.clone();
+        assert_eq!(etag, format!("\"{:016x}\"", data::stable_hash("<p>hi</p>")));
+
+        let response = respond(&request("GET", "/", &[]), &page);
+        assert!(response.headers.contains(&("ETag", etag.clone())));
+
+        for tags in [
+            etag.clone(),
+            format!("W/{etag}"),
+            format!("\"x\", {etag}"),
+            "*".into(),
+        ] {
+            let response = respond(&request("GET", "/", &[("if-none-match", &tags)]), &page);
+            assert_eq!(response.status, 304, "If-None-Match: {tags}");
+            assert!(response.body.is_empty());
+        }
+
+        let stale = respond(&request("GET", "/", &[("if-none-match", "\"x\"")]), &page);
+        assert_eq!(stale.status, 200);
+
+        let changed = Page::new("<p>bye</p>".into(), "{}".into());
+        let response = respond(&request("GET", "/", &[("if-none-match", &etag)]), &changed);
+        assert_eq!(response.status, 200);
+        assert!(response.headers.contains(&("ETag", changed.etag.clone())));
+    }
+
+    #[test]
+    fn gzips_html_if_accepted() {
+        let page = Page::new("<p>hi</p>".repeat(100), "{}".into());
+        let response = respond(
+            &request("GET", "/", &[("accept-encoding", "br, gzip")]),
+            &page,
+        );
+
+        assert!(
+            response
+                .headers
+                .contains(&("Content-Encoding", "gzip".into()))
+        );
+        assert!(response.headers.contains(&("ETag", page.gzip_etag.clone())));
+        assert_ne!(page.gzip_etag, page.etag);
+        let mut html = String::new();
+        flate2::read::GzDecoder::new(response.body.as_slice())
+            .read_to_string(&mut html)
+            .unwrap();
+        assert_eq!(html, page.html);
+
+        let gzip_etag = page.gzip_etag.as_str();
+        let headers = [("accept-encoding", "gzip"), ("if-none-match", gzip_etag)];
+        assert_eq!(respond(&request("GET", "/", &headers), &page).status, 304);
+    }
+
+    #[test]
+    fn accepts_gzip_unless_refused() {
+        let accepts =
+            |value: &str| request("GET", "/", &[("accept-encoding", value)]).accepts_gzip();
+        assert!(accepts("gzip"));
+        assert!(accepts("deflate, GZIP;q=0.5"));
+        assert!(accepts("x-gzip"));
+        assert!(accepts("*"));
+        assert!(!accepts("gzip;q=0"));
+        assert!(!accepts("gzip;q=0, *"));
+        assert!(!accepts("br, deflate"));
+        assert!(!accepts("identity"));
+        assert!(!request("GET", "/", &[]).accepts_gzip());
+
+        let page = Page::new("<p>hi</p>".into(), "{}".into());
+        let response = respond(&request("GET", "/", &[("accept-encoding", "br")]), &page);
+        assert_eq!(response.body, b"<p>hi</p>");
+        assert!(
+            !response
+                .headers
+                .iter()
+                .any(|(name, _)| *name == "Content-Encoding")
+        );
+    }
+
+    #[test]
+    fn serves_fetch_report_as_json() {
+        let page = Page::new("<p>hi</p>".into(), r#"{"feeds":[]}"#.into());
+        let response = respond(&request("GET", "/feeds.json", &[]), &page);
+
+        assert_eq!(response.status, 200);
+        assert!(
+            response
+                .headers
+                .contains(&("Content-Type", "application/json".into()))
+        );
+        assert_eq!(response.body, br#"{"feeds":[]}"#);
+    }
+
+    #[test]
+    fn head_responses_keep_content_length_but_omit_body() {
+        let page = Page::new("<p>hi</p>".into(), "{}".into());
+        let response = respond(&request("HEAD", "/", &[]), &page).head_only(true);
+
+        let mut written = Vec::new();
+        response.write_to(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(written.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(written.contains("Content-Length: 9\r\n"));
+        assert!(written.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn local_url_replaces_unspecified_addresses() {
+        let url = |addr: &str| local_url(addr.parse().unwrap());
+        assert_eq!(url("0.0.0.0:9005"), "http://127.0.0.1:9005/");
+        assert_eq!(url("[::]:9005"), "http://[::1]:9005/");
+        assert_eq!(url("192.168.1.2:80"), "http://192.168.1.2:80/");
+    }
+}